@@ -0,0 +1,52 @@
+use std::{fs::File, io::BufWriter, path::Path};
+
+use image::{
+    codecs::gif::{GifEncoder, Repeat},
+    imageops::FilterType,
+    Delay, Frame,
+};
+
+/// Encodes the given JPEG frames, in order, into a looping animated GIF.
+///
+/// Each frame is scaled down to fit within `max_size` pixels per side since full resolution GIFs
+/// are enormous and slow to encode. This is CPU bound, so call it from a blocking thread.
+pub fn encode_gif<P: AsRef<Path>>(
+    frames: &[P],
+    output: &Path,
+    max_size: u32,
+    fps: u32,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let delay = Delay::from_numer_denom_ms(1000, fps.max(1));
+    let mut encoder = GifEncoder::new_with_speed(BufWriter::new(File::create(output)?), 10);
+    encoder.set_repeat(Repeat::Infinite)?;
+    for path in frames {
+        let image = image::open(path)?.resize(max_size, max_size, FilterType::Triangle);
+        encoder.encode_frame(Frame::from_parts(image.to_rgba8(), 0, 0, delay))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use image::{Rgb, RgbImage};
+
+    use super::*;
+
+    #[test]
+    fn encodes_jpeg_frames_to_gif() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let dir = tempfile::tempdir()?;
+        let frames: Vec<_> = (0..2u8)
+            .map(|i| {
+                let path = dir.path().join(format!("{i}.jpg"));
+                RgbImage::from_pixel(64, 64, Rgb([i * 100, 0, 0]))
+                    .save(&path)
+                    .map(|_| path)
+            })
+            .collect::<Result<_, _>>()?;
+        let output = dir.path().join("out.gif");
+        encode_gif(&frames, &output, 32, 4)?;
+        let bytes = std::fs::read(&output)?;
+        assert!(bytes.starts_with(b"GIF89a"));
+        Ok(())
+    }
+}
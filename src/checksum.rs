@@ -0,0 +1,106 @@
+use std::{collections::BTreeMap, io, path::Path};
+
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+/// Lowercase hex SHA-256 of `bytes`, in the same form `sha256sum` prints
+pub fn sha256_hex(bytes: &[u8]) -> String {
+    Sha256::digest(bytes)
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect()
+}
+
+/// Formats one line of a `sha256sum -c` compatible checksum file
+pub fn checksum_line(hash: &str, file_name: &str) -> String {
+    format!("{hash}  {file_name}\n")
+}
+
+/// How the images in a directory differ from a reference directory, by file name
+#[derive(Serialize, Debug, Default, PartialEq, Eq)]
+pub struct DirComparison {
+    /// Only present in the new directory
+    pub added: Vec<String>,
+    /// Only present in the reference directory
+    pub removed: Vec<String>,
+    /// Present in both with different contents
+    pub changed: Vec<String>,
+    /// Present in both with identical contents
+    pub identical: Vec<String>,
+}
+
+/// Hashes every `.jpg` or `.jpeg` (any case, as a --name-template may produce) directly inside
+/// `dir`, keyed by file name. Symlinks such as `latest.jpg`
+/// are skipped since they only alias another frame
+fn hash_images(dir: &Path) -> io::Result<BTreeMap<String, String>> {
+    let mut hashes = BTreeMap::new();
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        let is_jpeg = path
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("jpg") || ext.eq_ignore_ascii_case("jpeg"));
+        if entry.file_type()?.is_file() && is_jpeg {
+            let name = path.file_name().unwrap().to_string_lossy().to_string();
+            hashes.insert(name, sha256_hex(&std::fs::read(&path)?));
+        }
+    }
+    Ok(hashes)
+}
+
+/// Compares the JPEG files of `new` against those of `reference` by SHA-256. Blocking
+pub fn compare_dirs(new: &Path, reference: &Path) -> io::Result<DirComparison> {
+    let new = hash_images(new)?;
+    let mut reference = hash_images(reference)?;
+    let mut comparison = DirComparison::default();
+    for (name, hash) in new {
+        match reference.remove(&name) {
+            None => comparison.added.push(name),
+            Some(old) if old == hash => comparison.identical.push(name),
+            Some(_) => comparison.changed.push(name),
+        }
+    }
+    comparison.removed = reference.into_keys().collect();
+    Ok(comparison)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn known_digest() {
+        assert_eq!(
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855",
+            sha256_hex(b"")
+        );
+    }
+
+    #[test]
+    fn compares_directories() -> io::Result<()> {
+        let root = tempfile::tempdir()?;
+        let (new, reference) = (root.path().join("new"), root.path().join("ref"));
+        std::fs::create_dir_all(&new)?;
+        std::fs::create_dir_all(&reference)?;
+        std::fs::write(new.join("a.jpg"), "same")?;
+        std::fs::write(reference.join("a.jpg"), "same")?;
+        std::fs::write(new.join("b.jpg"), "new")?;
+        std::fs::write(reference.join("b.jpg"), "old")?;
+        std::fs::write(new.join("c.jpg"), "added")?;
+        std::fs::write(new.join("e.jpeg"), "same")?;
+        std::fs::write(reference.join("e.jpeg"), "same")?;
+        std::fs::write(reference.join("d.jpg"), "removed")?;
+        std::fs::write(new.join("notes.txt"), "ignored")?;
+        let comparison = compare_dirs(&new, &reference);
+        assert_eq!(
+            DirComparison {
+                added: vec!["c.jpg".to_string()],
+                removed: vec!["d.jpg".to_string()],
+                changed: vec!["b.jpg".to_string()],
+                identical: vec!["a.jpg".to_string(), "e.jpeg".to_string()],
+            },
+            comparison?
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,64 @@
+use std::path::Path;
+
+use image::{imageops::FilterType, ImageFormat, Rgb, RgbImage};
+
+/// Tiles the given frames, in order, left to right and top to bottom into one JPEG at `output`.
+///
+/// Every frame is scaled to fit a `cell` pixel square and centered in it, so frames of any
+/// aspect ratio line up. The grid is as close to square as the frame count allows. This is CPU
+/// bound, so call it from a blocking thread.
+pub fn encode_contact_sheet<P: AsRef<Path>>(
+    frames: &[P],
+    output: &Path,
+    cell: u32,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    if frames.is_empty() {
+        return Err("no frames to put on a contact sheet".into());
+    }
+    let columns = (frames.len() as f64).sqrt().ceil() as u32;
+    let rows = (frames.len() as u32).div_ceil(columns);
+    let mut sheet = RgbImage::from_pixel(columns * cell, rows * cell, Rgb([0, 0, 0]));
+    for (i, path) in frames.iter().enumerate() {
+        let image = image::open(path)?
+            .resize(cell, cell, FilterType::Triangle)
+            .to_rgb8();
+        let (column, row) = (i as u32 % columns, i as u32 / columns);
+        let x = column * cell + (cell - image.width()) / 2;
+        let y = row * cell + (cell - image.height()) / 2;
+        image::imageops::replace(&mut sheet, &image, x.into(), y.into());
+    }
+    sheet.save_with_format(output, ImageFormat::Jpeg)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tiles_frames_into_a_square_grid() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let dir = tempfile::tempdir()?;
+        let frames: Vec<_> = (0..3u8)
+            .map(|i| {
+                let path = dir.path().join(format!("{i}.jpg"));
+                RgbImage::from_pixel(64, 32, Rgb([255, 255, 255]))
+                    .save(&path)
+                    .map(|_| path)
+            })
+            .collect::<Result<_, _>>()?;
+        let output = dir.path().join("sheet.jpg");
+        encode_contact_sheet(&frames, &output, 16)?;
+
+        let sheet = image::open(&output)?.to_rgb8();
+        assert_eq!((32, 32), sheet.dimensions());
+        let brightness = |x, y| sheet.get_pixel(x, y)[0];
+        // 64x32 frames become 16x8, centered vertically in their cells
+        assert!(brightness(8, 1) < 50);
+        assert!(brightness(8, 8) > 200);
+        assert!(brightness(24, 8) > 200);
+        assert!(brightness(8, 24) > 200);
+        // Three frames leave the last cell empty
+        assert!(brightness(24, 24) < 50);
+        Ok(())
+    }
+}
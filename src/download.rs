@@ -0,0 +1,1218 @@
+use std::{
+    collections::BTreeMap,
+    fs::File,
+    io::Write,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+};
+
+use chrono::{DateTime, TimeDelta, Utc};
+use chrono_tz::Tz;
+use futures::StreamExt;
+use reqwest::{Client, StatusCode};
+use tracing::Instrument;
+use url::Url;
+
+use crate::{
+    checksum,
+    goesimages::{Product, Resolution, Sat, Sector},
+    jpeg,
+    plan::{self, NameTemplate, PlanOptions, PlannedFrame},
+};
+
+/// Everything that can go wrong planning or downloading frames
+#[derive(Debug, thiserror::Error)]
+pub enum DownloadError {
+    #[error("{0}")]
+    InvalidDuration(String),
+    #[error("{0}")]
+    InvalidRange(String),
+    /// A frame URL couldn't be built, e.g. for a product and resolution NOAA doesn't publish
+    #[error("{0}")]
+    Plan(String),
+    #[error("Failed to build HTTP client: {0}")]
+    Client(#[source] reqwest::Error),
+    /// No response arrived, like a refused connection or a timeout
+    #[error("Failed to fetch {url}: {source}")]
+    Request {
+        url: Url,
+        #[source]
+        source: reqwest::Error,
+    },
+    #[error("Failed to fetch {url}: HTTP{status}")]
+    Status { url: Url, status: StatusCode },
+    #[error("Failed to read response: {0}")]
+    Body(#[source] reqwest::Error),
+    /// A redirect or Content-Disposition header named a different file than the one requested
+    #[error("Requested {expected} but the CDN served {served}")]
+    WrongFile { expected: String, served: String },
+    #[error("{url} is {}x{}, expected {}x{}", actual.0, actual.1, expected.0, expected.1)]
+    Dimensions {
+        url: Url,
+        actual: (u32, u32),
+        expected: (u32, u32),
+    },
+    #[error("{url} has no readable JPEG header")]
+    NotJpeg { url: Url },
+    /// A 200 whose body isn't a whole JPEG, like an HTML error page or a truncated transfer
+    #[error("{url} is not a complete JPEG: {reason}")]
+    Corrupt { url: Url, reason: String },
+    /// [`FetchConfig::retry_total_timeout`] ran out, however many retries were left
+    #[error("Gave up on {url} after trying for {limit:?}")]
+    GaveUp {
+        url: Url,
+        limit: std::time::Duration,
+    },
+    #[error("Read-back mismatch for {path}: downloaded {downloaded}, on disk {on_disk}")]
+    ReadBack {
+        path: String,
+        downloaded: String,
+        on_disk: String,
+    },
+    #[error("Failed to {action} {path}: {source}")]
+    Io {
+        action: &'static str,
+        path: String,
+        #[source]
+        source: std::io::Error,
+    },
+}
+
+impl DownloadError {
+    /// Whether trying the same frame again could succeed: connection problems, 5xx responses
+    /// and corrupt bodies, but not a 404 for a frame that doesn't exist
+    pub fn is_transient(&self) -> bool {
+        match self {
+            DownloadError::Request { .. }
+            | DownloadError::Body(_)
+            | DownloadError::Corrupt { .. } => true,
+            DownloadError::Status { status, .. } => status.is_server_error(),
+            _ => false,
+        }
+    }
+
+    /// HTTP status the CDN answered with, if the request got that far
+    pub fn status(&self) -> Option<u16> {
+        match self {
+            DownloadError::Status { status, .. } => Some(status.as_u16()),
+            _ => None,
+        }
+    }
+}
+
+/// Parses a span like "2d12h20m" or "90s" with no restriction on granularity. Every number has
+/// to be followed by its unit
+pub fn parse_span(input: &str) -> Result<TimeDelta, DownloadError> {
+    let invalid = |message: String| Err(DownloadError::InvalidDuration(message));
+    if input.is_empty() {
+        return invalid("Duration is empty".to_string());
+    }
+    let mut total_seconds = 0;
+    let mut value = String::new();
+
+    for c in input.chars() {
+        if c.is_ascii_digit() {
+            value.push(c);
+        } else {
+            if value.is_empty() {
+                return invalid(format!(
+                    "Duration unit '{c}' in '{input}' has no number before it"
+                ));
+            }
+            let Ok(num) = value.parse::<i64>() else {
+                return invalid("Invalid duration value".to_string());
+            };
+            value.clear();
+            let unit = match c {
+                's' => 1,
+                'm' => 60,
+                'h' => 3600,
+                'd' => 86400,
+                _ => return invalid("Unsupported duration unit. Use s, m, h, or d".to_string()),
+            };
+            let Some(total) = num
+                .checked_mul(unit)
+                .and_then(|seconds| seconds.checked_add(total_seconds))
+            else {
+                return invalid(format!("Duration '{input}' is too long"));
+            };
+            total_seconds = total;
+        }
+    }
+    if !value.is_empty() {
+        return invalid(format!(
+            "Duration '{input}' ends in '{value}' without a unit. Use s, m, h, or d"
+        ));
+    }
+
+    TimeDelta::try_seconds(total_seconds)
+        .map_or_else(|| invalid(format!("Duration '{input}' is too long")), Ok)
+}
+
+/// Parses a range duration, which has to line up with a sector's cadence of `minutes`, see
+/// [`Sector::min_stride_minutes`]
+pub fn parse_duration(input: &str, minutes: i64) -> Result<TimeDelta, DownloadError> {
+    let duration = parse_span(input)?;
+
+    if duration.num_seconds() % (minutes * 60) != 0 {
+        return Err(DownloadError::InvalidDuration(format!(
+            "Duration must be a multiple of {} minutes",
+            minutes
+        )));
+    }
+
+    Ok(duration)
+}
+
+/// Longest stride accepted, in minutes. NOAA only keeps about five days of frames, so a longer
+/// stride could never reach a second one
+pub const MAX_STRIDE_MINUTES: i64 = 5 * 24 * 60;
+
+/// Every `stride` minutes from `start` up to and including `end`. A stride too long to add to a
+/// time just ends the sequence
+pub fn frame_times(
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+    stride: i64,
+) -> impl Iterator<Item = DateTime<Utc>> {
+    let time_delta = TimeDelta::try_minutes(stride);
+    std::iter::successors(Some(start), move |&prev| -> Option<DateTime<Utc>> {
+        time_delta
+            .and_then(|delta| prev.checked_add_signed(delta))
+            .filter(|next| *next <= end)
+    })
+}
+
+/// Per-run settings shared by every download task
+pub struct FetchConfig {
+    pub options: PlanOptions,
+    /// Open SHA256SUMS file to record every frame in after reading it back from disk
+    pub checksums: Option<Mutex<File>>,
+    /// Number of responses received per negotiated HTTP version
+    pub http_versions: Mutex<BTreeMap<String, usize>>,
+    /// Fail a frame if a redirect or Content-Disposition names a different file
+    pub strict_filename_match: bool,
+    /// Whether to compute each frame's SHA-256 even without `checksums`
+    pub hash_frames: bool,
+    /// Fail a frame unless its JPEG header matches the requested dimensions
+    pub verify_dimensions: bool,
+    /// Extra attempts for a frame after a connection error or a 5xx
+    pub retries: u32,
+    /// Wait before the first retry, doubled for every one after it
+    pub retry_delay: std::time::Duration,
+    /// Longest a frame may take from its first attempt on, backoff and --nearest stand-ins
+    /// included. Once it's up the frame fails even with retries left
+    pub retry_total_timeout: Option<std::time::Duration>,
+    /// Skip frames whose file already exists and isn't empty
+    pub resume: bool,
+    /// Paces the start of every request, retries included, across all tasks
+    pub rate_limit: Option<RateLimiter>,
+    /// How many earlier publications of the sector to try in place of a frame that 404s, zero
+    /// to report it missing straight away
+    pub nearest_steps: u32,
+    /// Limit on each whole request, body included, on top of the client's own timeouts. See
+    /// [`TimeoutScaling`]
+    pub frame_timeout: Option<std::time::Duration>,
+}
+
+impl FetchConfig {
+    /// Plain downloads into `options.subdirectory` with the default retries and no checks
+    pub fn new(options: PlanOptions) -> Self {
+        FetchConfig {
+            options,
+            checksums: None,
+            http_versions: Mutex::new(BTreeMap::new()),
+            strict_filename_match: false,
+            hash_frames: false,
+            verify_dimensions: false,
+            retries: DEFAULT_RETRIES,
+            retry_delay: RETRY_BASE_DELAY,
+            retry_total_timeout: None,
+            resume: false,
+            rate_limit: None,
+            nearest_steps: 0,
+            frame_timeout: None,
+        }
+    }
+}
+
+/// A per-request time limit that grows with the frame size, so a 10848x10848 frame gets more
+/// time than a thumbnail:
+///
+/// `limit = base + per_megapixel × width × height / 1,000,000`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TimeoutScaling {
+    pub base: std::time::Duration,
+    pub per_megapixel: std::time::Duration,
+}
+
+impl TimeoutScaling {
+    /// The limit for a `(width, height)` frame
+    pub fn for_dimensions(&self, (width, height): (u32, u32)) -> std::time::Duration {
+        let megapixels = f64::from(width) * f64::from(height) / 1e6;
+        let scaled =
+            std::time::Duration::try_from_secs_f64(self.per_megapixel.as_secs_f64() * megapixels)
+                .unwrap_or(std::time::Duration::MAX);
+        self.base.saturating_add(scaled)
+    }
+}
+
+/// Keeps request starts at least `interval` apart across every task sharing it, independently
+/// of how many requests are in flight at once
+#[derive(Debug)]
+pub struct RateLimiter {
+    interval: std::time::Duration,
+    next: Mutex<tokio::time::Instant>,
+}
+
+/// Longest gap a [`RateLimiter`] keeps between two requests, one a day
+pub const MAX_RATE_INTERVAL: std::time::Duration = std::time::Duration::from_secs(86400);
+
+impl RateLimiter {
+    /// Intervals beyond [`MAX_RATE_INTERVAL`] are shortened to it
+    pub fn new(interval: std::time::Duration) -> Self {
+        RateLimiter {
+            interval: interval.min(MAX_RATE_INTERVAL),
+            next: Mutex::new(tokio::time::Instant::now()),
+        }
+    }
+
+    /// At most `requests` starts per second, but never fewer than one a day
+    pub fn per_second(requests: f64) -> Self {
+        let interval =
+            std::time::Duration::try_from_secs_f64(1.0 / requests).unwrap_or(MAX_RATE_INTERVAL);
+        RateLimiter::new(interval)
+    }
+
+    /// Waits for the next free slot. Slots are handed out in call order, so a task that has to
+    /// wait has already reserved its place
+    pub async fn wait(&self) {
+        let slot = {
+            let mut next = self.next.lock().unwrap();
+            let slot = (*next).max(tokio::time::Instant::now());
+            *next = slot + self.interval;
+            slot
+        };
+        tokio::time::sleep_until(slot).await;
+    }
+}
+
+/// What was written for one successfully downloaded frame
+#[derive(Clone, Debug)]
+pub struct SavedImage {
+    pub path: String,
+    pub bytes: u64,
+    /// HTTP status of the response, `None` for frames kept from an earlier run
+    pub status: Option<u16>,
+    pub sha256: Option<String>,
+    pub etag: Option<String>,
+    /// Left alone by [`FetchConfig::resume`] because an earlier run saved it
+    pub already_present: bool,
+    /// Time of the earlier frame actually saved when [`FetchConfig::nearest_steps`] stood it in
+    /// for a planned frame that doesn't exist
+    pub substituted: Option<DateTime<Utc>>,
+    /// Attempts after the first that it took, see [`FetchConfig::retries`]
+    pub retries: u32,
+}
+
+/// Sends a GET, retrying once straight away if it failed before any response arrived. The first
+/// request after a quiet period can land on a pooled connection the CDN has already closed, which
+/// fails without the request ever being served; timeouts are not retried. Only for one-off
+/// requests: frames go through [`FetchConfig::retries`] instead, which paces every attempt
+pub async fn send_with_reconnect(client: &Client, url: &Url) -> reqwest::Result<reqwest::Response> {
+    match client.get(url.clone()).send().await {
+        Err(e) if !e.is_timeout() && (e.is_request() || e.is_connect()) => {
+            client.get(url.clone()).send().await
+        }
+        result => result,
+    }
+}
+
+/// Last path segment of a URL, i.e. the NOAA file name
+pub fn url_file_name(url: &Url) -> Option<&str> {
+    url.path_segments().and_then(|mut s| s.next_back())
+}
+
+/// Extracts the `filename` parameter from a Content-Disposition header value
+fn content_disposition_filename(header: &str) -> Option<String> {
+    header.split(';').find_map(|param| {
+        let (key, value) = param.trim().split_once('=')?;
+        key.trim()
+            .eq_ignore_ascii_case("filename")
+            .then(|| value.trim().trim_matches('"').to_string())
+    })
+}
+
+/// Errors if the response identifies itself as a different file than the one requested
+fn check_served_file_name(
+    requested: &Url,
+    response: &reqwest::Response,
+) -> Result<(), DownloadError> {
+    let expected = url_file_name(requested).unwrap_or_default();
+    let served = url_file_name(response.url()).unwrap_or_default();
+    if served != expected {
+        return Err(DownloadError::WrongFile {
+            expected: expected.to_string(),
+            served: response.url().to_string(),
+        });
+    }
+    if let Some(name) = response
+        .headers()
+        .get(reqwest::header::CONTENT_DISPOSITION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(content_disposition_filename)
+    {
+        if name != expected {
+            return Err(DownloadError::WrongFile {
+                expected: expected.to_string(),
+                served: name,
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Extra attempts a frame gets unless configured otherwise
+pub const DEFAULT_RETRIES: u32 = 3;
+/// Wait before the first retry of a frame, doubled for each further retry
+pub const RETRY_BASE_DELAY: std::time::Duration = std::time::Duration::from_millis(500);
+/// Longest wait between two attempts at a frame
+const RETRY_MAX_DELAY: std::time::Duration = std::time::Duration::from_secs(30);
+
+/// One GET of `url`, returning the status, ETag and body
+async fn download_once(
+    client: &Client,
+    config: &FetchConfig,
+    url: &Url,
+) -> Result<(StatusCode, Option<String>, bytes::Bytes), DownloadError> {
+    if let Some(rate_limit) = &config.rate_limit {
+        rate_limit.wait().await;
+    }
+    tracing::debug!(%url, "Requesting");
+    let mut request = client.get(url.clone());
+    if let Some(timeout) = config.frame_timeout {
+        request = request.timeout(timeout);
+    }
+    // A dropped pooled connection is retried by fetch_frame like any other request error
+    let response = request
+        .send()
+        .await
+        .map_err(|source| DownloadError::Request {
+            url: url.clone(),
+            source,
+        })?;
+
+    *config
+        .http_versions
+        .lock()
+        .unwrap()
+        .entry(format!("{:?}", response.version()))
+        .or_default() += 1;
+
+    let status = response.status();
+    if !status.is_success() {
+        return Err(DownloadError::Status {
+            url: url.clone(),
+            status,
+        });
+    }
+
+    if config.strict_filename_match {
+        check_served_file_name(url, &response)?;
+    }
+
+    let corrupt = |reason: String| DownloadError::Corrupt {
+        url: url.clone(),
+        reason,
+    };
+    // A missing Content-Type is left to the marker check below
+    if let Some(content_type) = response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .map(|value| value.to_str().unwrap_or_default())
+    {
+        if !content_type.trim_start().starts_with("image/") {
+            return Err(corrupt(format!("served as '{content_type}'")));
+        }
+    }
+    let etag = response
+        .headers()
+        .get(reqwest::header::ETAG)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string);
+    let bytes = response.bytes().await.map_err(DownloadError::Body)?;
+    if let Some(reason) = jpeg::incomplete(&bytes) {
+        return Err(corrupt(reason.to_string()));
+    }
+    Ok((status, etag, bytes))
+}
+
+/// Exponential backoff for the zero-based `attempt`, with up to half of it taken off at random
+/// so frames that failed together don't all retry together
+fn retry_delay(base: std::time::Duration, attempt: u32) -> std::time::Duration {
+    use std::hash::{BuildHasher, Hasher};
+
+    let delay = base
+        .saturating_mul(1 << attempt.min(16))
+        .min(RETRY_MAX_DELAY);
+    let jitter = std::collections::hash_map::RandomState::new()
+        .build_hasher()
+        .finish()
+        % 1000;
+    delay - delay / 2 * jitter as u32 / 1000
+}
+
+/// How far back `--nearest` looks for a frame to stand in for a missing one
+pub const NEAREST_MAX_STEPS: u32 = 3;
+
+/// Downloads one planned frame to its path, retrying transient failures. If the frame 404s and
+/// [`FetchConfig::nearest_steps`] allows it, the closest earlier publication of the sector is
+/// saved under its own name instead
+pub async fn fetch_image(
+    client: Client,
+    config: Arc<FetchConfig>,
+    frame: &PlannedFrame,
+) -> Result<SavedImage, DownloadError> {
+    fetch_image_with_retries(client, config, frame).await.0
+}
+
+/// [`fetch_image`], also returning how many retries it took whether it succeeded in the end or
+/// not. Retries of --nearest stand-ins count towards the frame they stand in for
+pub async fn fetch_image_with_retries(
+    client: Client,
+    config: Arc<FetchConfig>,
+    frame: &PlannedFrame,
+) -> (Result<SavedImage, DownloadError>, u32) {
+    let mut attempts = Attempts {
+        retries: 0,
+        deadline: config
+            .retry_total_timeout
+            .map(|limit| (tokio::time::Instant::now() + limit, limit)),
+    };
+    let result = fetch_or_nearest(&client, &config, frame, &mut attempts)
+        .await
+        .map(|image| SavedImage {
+            retries: attempts.retries,
+            ..image
+        });
+    (result, attempts.retries)
+}
+
+/// Bookkeeping for every attempt at one planned frame
+struct Attempts {
+    retries: u32,
+    /// When [`FetchConfig::retry_total_timeout`] runs out, and the limit itself
+    deadline: Option<(tokio::time::Instant, std::time::Duration)>,
+}
+
+async fn fetch_or_nearest(
+    client: &Client,
+    config: &FetchConfig,
+    frame: &PlannedFrame,
+    attempts: &mut Attempts,
+) -> Result<SavedImage, DownloadError> {
+    let missing = match fetch_frame(client, config, frame, None, attempts).await {
+        Err(e) if e.status() == Some(StatusCode::NOT_FOUND.as_u16()) => e,
+        result => return result,
+    };
+    let cadence = TimeDelta::minutes(frame.sector.cadence_minutes());
+    for step in 1..=config.nearest_steps {
+        let time = frame.timestamp - cadence * step as i32;
+        let candidate = plan::plan(&config.options, [time])
+            .map_err(DownloadError::Plan)?
+            .entries
+            .remove(0);
+        match fetch_frame(client, config, &candidate, Some(frame), attempts).await {
+            Ok(image) => {
+                tracing::info!("{} is missing, saved {} instead", frame.url, candidate.url);
+                return Ok(SavedImage {
+                    substituted: Some(time),
+                    ..image
+                });
+            }
+            Err(e) if e.status() == Some(StatusCode::NOT_FOUND.as_u16()) => continue,
+            Err(e) => return Err(e),
+        }
+    }
+    Err(missing)
+}
+
+/// Downloads `frame` to its path, retrying transient failures and counting them in `attempts`.
+/// `stand_in_for` names the planned frame it replaces, which keeps its staging file apart from
+/// the one a planned download of the same frame would use
+async fn fetch_frame(
+    client: &Client,
+    config: &FetchConfig,
+    frame: &PlannedFrame,
+    stand_in_for: Option<&PlannedFrame>,
+    attempts: &mut Attempts,
+) -> Result<SavedImage, DownloadError> {
+    if config.resume {
+        match tokio::fs::metadata(&frame.path).await {
+            Ok(metadata) if metadata.is_file() && metadata.len() > 0 => {
+                return Ok(SavedImage {
+                    path: frame.path.clone(),
+                    bytes: metadata.len(),
+                    status: None,
+                    sha256: None,
+                    etag: None,
+                    already_present: true,
+                    substituted: None,
+                    retries: 0,
+                })
+            }
+            _ => {}
+        }
+    }
+
+    let url = &frame.url;
+    let mut attempt = 0;
+    let gave_up = |limit| DownloadError::GaveUp {
+        url: url.clone(),
+        limit,
+    };
+    let (status, etag, bytes) = loop {
+        let downloaded = match attempts.deadline {
+            // Only the response is cut short, nothing has been written yet
+            Some((deadline, limit)) => {
+                tokio::time::timeout_at(deadline, download_once(client, config, url))
+                    .await
+                    .unwrap_or_else(|_| Err(gave_up(limit)))
+            }
+            None => download_once(client, config, url).await,
+        };
+        match downloaded {
+            Ok(downloaded) => break downloaded,
+            Err(e) if e.is_transient() && attempt < config.retries => {
+                let delay = retry_delay(config.retry_delay, attempt);
+                if let Some((deadline, limit)) = attempts.deadline {
+                    if tokio::time::Instant::now() + delay >= deadline {
+                        tracing::warn!("{e}, out of time to retry");
+                        return Err(gave_up(limit));
+                    }
+                }
+                attempt += 1;
+                attempts.retries += 1;
+                tracing::warn!(
+                    "{e}, retrying in {:.1}s ({attempt}/{})",
+                    delay.as_secs_f64(),
+                    config.retries
+                );
+                tokio::time::sleep(delay).await;
+            }
+            Err(e) => return Err(e),
+        }
+    };
+
+    let file_name = &frame.file_name;
+    let image_path = frame.path.clone();
+    if config.verify_dimensions {
+        let expected = frame.dimensions;
+        match jpeg::dimensions(&bytes) {
+            Some(actual) if actual == expected => {}
+            Some(actual) => {
+                return Err(DownloadError::Dimensions {
+                    url: url.clone(),
+                    actual,
+                    expected,
+                })
+            }
+            None => return Err(DownloadError::NotJpeg { url: url.clone() }),
+        }
+    }
+    // Written beside the frame and renamed over it once complete, so an interrupted write never
+    // leaves a truncated .jpg behind
+    let partial_path = match stand_in_for {
+        None => format!("{image_path}.tmp"),
+        Some(planned) => format!("{image_path}.{}.tmp", planned.file_name),
+    };
+    let io_error = |action, path: &str, source| DownloadError::Io {
+        action,
+        path: path.to_string(),
+        source,
+    };
+    let hash =
+        (config.checksums.is_some() || config.hash_frames).then(|| checksum::sha256_hex(&bytes));
+    tokio::fs::write(&partial_path, &bytes)
+        .await
+        .map_err(|e| io_error("save", &partial_path, e))?;
+
+    if let (Some(_), Some(hash)) = (&config.checksums, &hash) {
+        let written = tokio::fs::read(&partial_path)
+            .await
+            .map_err(|e| io_error("read back", &partial_path, e))?;
+        let read_back = checksum::sha256_hex(&written);
+        if &read_back != hash {
+            let _ = tokio::fs::remove_file(&partial_path).await;
+            return Err(DownloadError::ReadBack {
+                path: image_path,
+                downloaded: hash.clone(),
+                on_disk: read_back,
+            });
+        }
+    }
+    tokio::fs::rename(&partial_path, &image_path)
+        .await
+        .map_err(|e| io_error("move into place", &partial_path, e))?;
+
+    if let (Some(checksums), Some(hash)) = (&config.checksums, &hash) {
+        checksums
+            .lock()
+            .unwrap()
+            .write_all(checksum::checksum_line(hash, file_name).as_bytes())
+            .map_err(|e| io_error("record checksum for", &image_path, e))?;
+    }
+
+    Ok(SavedImage {
+        path: image_path,
+        bytes: bytes.len() as u64,
+        status: Some(status.as_u16()),
+        sha256: hash,
+        etag,
+        already_present: false,
+        substituted: None,
+        retries: 0,
+    })
+}
+
+/// How many frames [`run`] downloads at once unless the request says otherwise
+pub const CONCURRENT_DOWNLOADS: usize = 8;
+
+/// Formats a frame time for log lines, in `tz` if one was given and UTC otherwise
+pub fn format_log_time(time: &DateTime<Utc>, tz: Option<&Tz>) -> String {
+    match tz {
+        None => time.to_string(),
+        Some(tz) => time
+            .with_timezone(tz)
+            .format("%Y-%m-%d %H:%M:%S %:z")
+            .to_string(),
+    }
+}
+
+/// A frame out of [`download_stream`]
+#[derive(Debug)]
+pub struct FinishedFrame {
+    pub frame: PlannedFrame,
+    /// The final error if the frame failed
+    pub result: Result<SavedImage, DownloadError>,
+    /// Attempts after the first, whether the frame succeeded in the end or not
+    pub retries: u32,
+    /// The `frame` span the download was logged in, to enter when logging about it afterwards
+    pub span: tracing::Span,
+}
+
+/// Downloads `frames` with at most `concurrency` in flight, yielding each as it finishes, so in
+/// completion order rather than time order. Frames are only started as earlier ones finish;
+/// once `keep_going` returns false no new frame is started and the stream ends after the ones
+/// already in flight
+pub fn download_stream<'a>(
+    client: Client,
+    config: Arc<FetchConfig>,
+    frames: impl IntoIterator<Item = PlannedFrame> + 'a,
+    concurrency: usize,
+    mut keep_going: impl FnMut() -> bool + 'a,
+) -> impl futures::Stream<Item = FinishedFrame> + 'a {
+    futures::stream::iter(frames)
+        .take_while(move |_| std::future::ready(keep_going()))
+        .map(move |frame| {
+            let client = client.clone();
+            let config = config.clone();
+            // Tags everything logged about this frame, since downloads interleave
+            let span = tracing::info_span!(
+                "frame",
+                time = %format_log_time(&frame.timestamp, config.options.timezone_output.as_ref())
+            );
+            async move {
+                let (result, retries) = fetch_image_with_retries(client, config, &frame).await;
+                FinishedFrame {
+                    frame,
+                    result,
+                    retries,
+                    span: tracing::Span::current(),
+                }
+            }
+            .instrument(span)
+        })
+        .buffer_unordered(concurrency.max(1))
+}
+
+/// A range of frames to save into `root`
+#[derive(Clone, Debug)]
+pub struct DownloadRequest {
+    pub sat: Sat,
+    pub sector: Sector,
+    pub product: Product,
+    pub resolution: Resolution,
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    /// Minutes between frames, a multiple of the sector's cadence
+    pub stride: i64,
+    /// Existing directory the frames are written into, named by their UTC timestamp
+    pub root: PathBuf,
+    /// Root of the CDN, see [`crate::goesimages::default_base_url`]
+    pub base_url: Url,
+    /// How many frames are downloaded at once, see [`CONCURRENT_DOWNLOADS`]
+    pub concurrency: usize,
+}
+
+impl DownloadRequest {
+    /// Full disk GEOCOLOR frames at the medium resolution, [`CONCURRENT_DOWNLOADS`] at a time
+    pub fn new(
+        sat: Sat,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+        stride: i64,
+        root: PathBuf,
+    ) -> Self {
+        DownloadRequest {
+            sat,
+            sector: Sector::FullDisk,
+            product: Product::GeoColor,
+            resolution: Resolution::Medium,
+            start,
+            end,
+            stride,
+            root,
+            base_url: crate::goesimages::default_base_url(),
+            concurrency: CONCURRENT_DOWNLOADS,
+        }
+    }
+}
+
+/// The outcome of [`run`], in frame order
+#[derive(Debug)]
+pub struct DownloadReport {
+    pub saved: Vec<(DateTime<Utc>, SavedImage)>,
+    pub missing: Vec<(DateTime<Utc>, DownloadError)>,
+    /// Attempts beyond the first across every frame, failed ones included
+    pub retries: u32,
+}
+
+/// Downloads every frame of `req`. Frames that fail are listed in the report; only a request
+/// that can't be started at all is an error
+pub async fn run(req: DownloadRequest) -> Result<DownloadReport, DownloadError> {
+    let cadence = req.sector.min_stride_minutes();
+    if req.stride <= 0 || req.stride % cadence != 0 || req.stride > MAX_STRIDE_MINUTES {
+        return Err(DownloadError::InvalidRange(format!(
+            "Stride ({}) must be a multiple of {} up to {}",
+            req.stride, cadence, MAX_STRIDE_MINUTES
+        )));
+    }
+    if req.end < req.start {
+        return Err(DownloadError::InvalidRange(format!(
+            "End time ({}) is before the start time ({})",
+            req.end, req.start
+        )));
+    }
+    if !req.root.is_dir() {
+        return Err(DownloadError::InvalidRange(format!(
+            "Root '{}' is not a directory",
+            req.root.display()
+        )));
+    }
+
+    let options = PlanOptions {
+        sat: req.sat,
+        sector: req.sector,
+        product: req.product,
+        resolution: req.resolution,
+        subdirectory: req.root.to_string_lossy().to_string(),
+        timezone_output: None,
+        name_template: NameTemplate::default(),
+        base_url: req.base_url,
+    };
+    let plan = plan::plan(&options, frame_times(req.start, req.end, req.stride))
+        .map_err(DownloadError::Plan)?;
+    let client = Client::builder().build().map_err(DownloadError::Client)?;
+    let config = Arc::new(FetchConfig::new(options));
+
+    let mut downloads = download_stream(client, config, plan.entries, req.concurrency, || true);
+    let mut report = DownloadReport {
+        saved: Vec::new(),
+        missing: Vec::new(),
+        retries: 0,
+    };
+    while let Some(finished) = downloads.next().await {
+        let time = finished.frame.timestamp;
+        report.retries += finished.retries;
+        match finished.result {
+            Ok(image) => report.saved.push((time, image)),
+            Err(e) => report.missing.push((time, e)),
+        }
+    }
+    report.saved.sort_by_key(|(time, _)| *time);
+    report.missing.sort_by_key(|(time, _)| *time);
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+    use crate::goesimages;
+
+    /// The smallest body that passes the JPEG marker check
+    const FRAME: &[u8] = b"\xFF\xD8\xFF\xD9";
+    const OK: &[u8] = b"HTTP/1.1 200 OK\r\nContent-Type: image/jpeg\r\nContent-Length: 4\r\nConnection: close\r\n\r\n\xFF\xD8\xFF\xD9";
+
+    /// Serves one canned response per connection, returning how many connections it accepted
+    async fn serve(responses: Vec<&'static [u8]>) -> (Url, tokio::task::JoinHandle<usize>) {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = Url::parse(&format!(
+            "http://{}/frame.jpg",
+            listener.local_addr().unwrap()
+        ))
+        .unwrap();
+        let handle = tokio::spawn(async move {
+            let mut served = 0;
+            for response in responses {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let mut request = [0; 1024];
+                let _ = stream.read(&mut request).await;
+                stream.write_all(response).await.unwrap();
+                served += 1;
+            }
+            served
+        });
+        (url, handle)
+    }
+
+    fn test_config(subdirectory: &std::path::Path) -> FetchConfig {
+        let mut config = FetchConfig::new(PlanOptions {
+            sat: Sat::GoesEast,
+            sector: Sector::FullDisk,
+            product: Product::GeoColor,
+            resolution: Resolution::Medium,
+            subdirectory: subdirectory.to_string_lossy().to_string(),
+            timezone_output: None,
+            name_template: NameTemplate::default(),
+            base_url: goesimages::default_base_url(),
+        });
+        config.retry_delay = std::time::Duration::from_millis(1);
+        config
+    }
+
+    fn planned_frame(config: &FetchConfig, time: DateTime<Utc>) -> PlannedFrame {
+        plan::plan(&config.options, [time])
+            .unwrap()
+            .entries
+            .remove(0)
+    }
+
+    #[tokio::test]
+    async fn retries_server_errors_but_not_missing_frames() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = Arc::new(test_config(dir.path()));
+        let time = Utc.with_ymd_and_hms(2024, 11, 30, 8, 30, 0).unwrap();
+        let mut frame = planned_frame(&config, time);
+
+        let unavailable: &[u8] =
+            b"HTTP/1.1 503 Service Unavailable\r\nContent-Length: 0\r\nConnection: close\r\n\r\n";
+        let (url, server) = serve(vec![unavailable, unavailable, OK]).await;
+        frame.url = url;
+        let saved = fetch_image(Client::new(), config.clone(), &frame)
+            .await
+            .unwrap();
+        assert_eq!(3, server.await.unwrap());
+        assert_eq!(FRAME.to_vec(), std::fs::read(&saved.path).unwrap());
+        assert!(!std::path::Path::new(&format!("{}.tmp", saved.path)).exists());
+        assert_eq!(2, saved.retries);
+
+        // A frame that runs out of retries still reports how many it had
+        let mut one_retry = test_config(dir.path());
+        one_retry.retries = 1;
+        let (url, server) = serve(vec![unavailable, unavailable]).await;
+        frame.url = url;
+        let (result, retries) =
+            fetch_image_with_retries(Client::new(), Arc::new(one_retry), &frame).await;
+        assert!(result.is_err());
+        assert_eq!(1, retries);
+        assert_eq!(2, server.await.unwrap());
+
+        let not_found: &[u8] =
+            b"HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n";
+        let (url, server) = serve(vec![not_found]).await;
+        frame.url = url;
+        let e = fetch_image(Client::new(), config, &frame)
+            .await
+            .err()
+            .unwrap();
+        assert!(
+            matches!(e, DownloadError::Status { status, .. } if status == StatusCode::NOT_FOUND),
+            "{e}"
+        );
+        assert!(e.to_string().ends_with("HTTP404 Not Found"), "{e}");
+        assert_eq!(1, server.await.unwrap());
+
+        // With --retries 0 a connection dropped before the response isn't tried again either
+        let mut config = test_config(dir.path());
+        config.retries = 0;
+        let (url, server) = serve(vec![b"", OK]).await;
+        frame.url = url;
+        let e = fetch_image(Client::new(), Arc::new(config), &frame)
+            .await
+            .err()
+            .unwrap();
+        assert!(matches!(e, DownloadError::Request { .. }), "{e}");
+        server.abort();
+    }
+
+    #[tokio::test]
+    async fn retry_total_timeout_cuts_retries_short() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = test_config(dir.path());
+        config.retries = 10;
+        config.retry_delay = std::time::Duration::from_millis(100);
+        config.retry_total_timeout = Some(std::time::Duration::from_millis(250));
+        let time = Utc.with_ymd_and_hms(2024, 11, 30, 8, 30, 0).unwrap();
+        let mut frame = planned_frame(&config, time);
+
+        let unavailable: &[u8] =
+            b"HTTP/1.1 503 Service Unavailable\r\nContent-Length: 0\r\nConnection: close\r\n\r\n";
+        let (url, server) = serve(vec![unavailable; 11]).await;
+        frame.url = url;
+        let started = std::time::Instant::now();
+        let (result, retries) =
+            fetch_image_with_retries(Client::new(), Arc::new(config), &frame).await;
+        // Ten retries would back off for over a minute
+        assert!(
+            started.elapsed() < std::time::Duration::from_millis(500),
+            "{:?}",
+            started.elapsed()
+        );
+        assert!(
+            matches!(result, Err(DownloadError::GaveUp { .. })),
+            "{result:?}"
+        );
+        // Backoff of at least 50ms, 100ms, 200ms... leaves room for one or two retries
+        assert!((1..=2).contains(&retries), "{retries}");
+        server.abort();
+    }
+
+    #[tokio::test]
+    async fn rejects_error_pages_and_truncated_frames() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = test_config(dir.path());
+        let time = Utc.with_ymd_and_hms(2024, 11, 30, 8, 30, 0).unwrap();
+        let mut frame = planned_frame(&config, time);
+
+        let html: &[u8] = b"HTTP/1.1 200 OK\r\nContent-Type: text/html\r\nContent-Length: 6\r\nConnection: close\r\n\r\n<html>";
+        let truncated: &[u8] = b"HTTP/1.1 200 OK\r\nContent-Type: image/jpeg\r\nContent-Length: 4\r\nConnection: close\r\n\r\n\xFF\xD8\xFF\xE0";
+        let (url, server) = serve(vec![html, truncated, OK]).await;
+        frame.url = url;
+        let saved = fetch_image(Client::new(), Arc::new(test_config(dir.path())), &frame)
+            .await
+            .unwrap();
+        assert_eq!(3, server.await.unwrap());
+        assert_eq!(FRAME.to_vec(), std::fs::read(&saved.path).unwrap());
+        std::fs::remove_file(&saved.path).unwrap();
+
+        config.retries = 0;
+        let config = Arc::new(config);
+        for (response, reason) in [(html, "text/html"), (truncated, "cut short")] {
+            let (url, server) = serve(vec![response]).await;
+            frame.url = url;
+            let e = fetch_image(Client::new(), config.clone(), &frame)
+                .await
+                .err()
+                .unwrap();
+            assert!(matches!(e, DownloadError::Corrupt { .. }), "{e}");
+            assert!(e.to_string().contains(reason), "{e}");
+            assert_eq!(1, server.await.unwrap());
+            assert!(!std::path::Path::new(&frame.path).exists());
+        }
+    }
+
+    #[tokio::test]
+    async fn nearest_falls_back_to_earlier_frames() {
+        let dir = tempfile::tempdir().unwrap();
+        let not_found: &[u8] =
+            b"HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n";
+        let time = Utc.with_ymd_and_hms(2024, 11, 30, 8, 30, 0).unwrap();
+
+        let (url, server) = serve(vec![not_found, not_found, OK]).await;
+        let mut config = test_config(dir.path());
+        config.options.base_url = url.join("/").unwrap();
+        config.nearest_steps = NEAREST_MAX_STEPS;
+        let frame = planned_frame(&config, time);
+        let saved = fetch_image(Client::new(), Arc::new(config), &frame)
+            .await
+            .unwrap();
+        assert_eq!(3, server.await.unwrap());
+        assert_eq!(Some(time - TimeDelta::minutes(20)), saved.substituted);
+        assert!(
+            saved.path.ends_with("20241130T081000.jpg"),
+            "{}",
+            saved.path
+        );
+        assert!(!std::path::Path::new(&frame.path).exists());
+
+        // The search gives up after NEAREST_MAX_STEPS earlier frames
+        let (url, server) = serve(vec![not_found; 4]).await;
+        let mut config = test_config(dir.path());
+        config.options.base_url = url.join("/").unwrap();
+        config.nearest_steps = NEAREST_MAX_STEPS;
+        let frame = planned_frame(&config, time);
+        let e = fetch_image(Client::new(), Arc::new(config), &frame)
+            .await
+            .err()
+            .unwrap();
+        assert_eq!(Some(404), e.status());
+        assert_eq!(4, server.await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn resume_skips_only_complete_frames() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = test_config(dir.path());
+        config.resume = true;
+        let config = Arc::new(config);
+        let first = Utc.with_ymd_and_hms(2024, 11, 30, 8, 30, 0).unwrap();
+        let second = Utc.with_ymd_and_hms(2024, 11, 30, 8, 40, 0).unwrap();
+        let mut frames = plan::plan(&config.options, [first, second])
+            .unwrap()
+            .entries;
+        std::fs::write(&frames[0].path, b"done").unwrap();
+        std::fs::write(&frames[1].path, b"").unwrap();
+
+        let (url, server) = serve(vec![OK]).await;
+        for frame in &mut frames {
+            frame.url = url.clone();
+        }
+        let skipped = fetch_image(Client::new(), config.clone(), &frames[0])
+            .await
+            .unwrap();
+        let fetched = fetch_image(Client::new(), config, &frames[1])
+            .await
+            .unwrap();
+        assert!(skipped.already_present);
+        assert!(!fetched.already_present);
+        assert_eq!(1, server.await.unwrap());
+        assert_eq!(FRAME.to_vec(), std::fs::read(&frames[1].path).unwrap());
+    }
+
+    #[tokio::test]
+    async fn rate_limit_spaces_requests() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = test_config(dir.path());
+        config.rate_limit = Some(RateLimiter::per_second(20.0));
+        let config = Arc::new(config);
+        let start = Utc.with_ymd_and_hms(2024, 11, 30, 8, 0, 0).unwrap();
+        let end = Utc.with_ymd_and_hms(2024, 11, 30, 8, 40, 0).unwrap();
+        let mut frames = plan::plan(&config.options, frame_times(start, end, 10))
+            .unwrap()
+            .entries;
+
+        let (url, server) = serve(vec![OK; 5]).await;
+        for frame in &mut frames {
+            frame.url = url.clone();
+        }
+        let started = std::time::Instant::now();
+        let results = futures::future::join_all(
+            frames
+                .iter()
+                .map(|frame| fetch_image(Client::new(), config.clone(), frame)),
+        )
+        .await;
+        // All five start concurrently, but four 50ms gaps separate their requests
+        assert!(started.elapsed() >= std::time::Duration::from_millis(200));
+        assert!(results.iter().all(Result::is_ok));
+        assert_eq!(5, server.await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn run_rejects_bad_requests() {
+        let start = Utc.with_ymd_and_hms(2024, 11, 30, 8, 30, 0).unwrap();
+        let req = DownloadRequest::new(Sat::GoesEast, start, start, 5, std::env::temp_dir());
+        assert!(matches!(
+            run(req).await,
+            Err(DownloadError::InvalidRange(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn run_downloads_from_base_url() {
+        let root = tempfile::tempdir().unwrap();
+        let (url, server) = serve(vec![OK]).await;
+        let start = Utc.with_ymd_and_hms(2024, 11, 30, 8, 30, 0).unwrap();
+        let report = run(DownloadRequest {
+            base_url: url.join("/").unwrap(),
+            ..DownloadRequest::new(Sat::GoesEast, start, start, 10, root.path().to_path_buf())
+        })
+        .await
+        .unwrap();
+        assert_eq!(1, server.await.unwrap());
+        assert!(report.missing.is_empty());
+        assert_eq!(
+            FRAME.to_vec(),
+            std::fs::read(root.path().join("20241130T083000.jpg")).unwrap()
+        );
+    }
+
+    #[test]
+    fn frame_timeouts_scale_with_megapixels() {
+        let scaling = TimeoutScaling {
+            base: std::time::Duration::from_secs(30),
+            per_megapixel: std::time::Duration::from_millis(500),
+        };
+        let secs = |dimensions| scaling.for_dimensions(dimensions).as_secs_f64();
+        assert_eq!(30.0, secs((0, 0)));
+        assert_eq!(32.0, secs((2000, 2000)));
+        // 117.7 megapixels
+        assert!((secs((10848, 10848)) - 88.84).abs() < 0.01);
+    }
+
+    #[test]
+    fn parses_spans_and_durations() {
+        assert_eq!(TimeDelta::seconds(90), parse_span("1m30s").unwrap());
+        assert_eq!(
+            TimeDelta::minutes(1450),
+            parse_duration("1d10m", 10).unwrap()
+        );
+        assert!(parse_duration("5m", 10).is_err());
+        assert_eq!(TimeDelta::minutes(5), parse_duration("5m", 5).unwrap());
+        assert_eq!(TimeDelta::minutes(3), parse_duration("3m", 1).unwrap());
+        assert!(parse_duration("30s", 1).is_err());
+        for huge in [
+            "200000000000d",
+            "9223372036854775807s1s",
+            "99999999999999999999m",
+        ] {
+            assert!(parse_span(huge).is_err(), "{huge}");
+        }
+    }
+
+    #[test]
+    fn rejects_numbers_without_units() {
+        let error = |input| parse_duration(input, 10).unwrap_err().to_string();
+        assert_eq!(
+            "Duration '120' ends in '120' without a unit. Use s, m, h, or d",
+            error("120")
+        );
+        assert_eq!(
+            "Duration '2h30' ends in '30' without a unit. Use s, m, h, or d",
+            error("2h30")
+        );
+        assert_eq!("Duration is empty", error(""));
+        assert_eq!(
+            "Duration unit 'h' in 'h' has no number before it",
+            error("h")
+        );
+    }
+
+    #[test]
+    fn parses_content_disposition_filename() {
+        assert_eq!(
+            Some("a.jpg".to_string()),
+            content_disposition_filename(r#"attachment; filename="a.jpg""#)
+        );
+        assert_eq!(
+            Some("b.jpg".to_string()),
+            content_disposition_filename("inline;FileName=b.jpg")
+        );
+        assert_eq!(None, content_disposition_filename("inline"));
+    }
+}
@@ -0,0 +1,163 @@
+use serde::Serialize;
+#[cfg(unix)]
+use std::path::PathBuf;
+#[cfg(unix)]
+use tokio::{io::AsyncWriteExt, sync::mpsc, task::JoinHandle};
+
+/// How many events are held for a slow or absent consumer before new ones are dropped
+pub const EVENT_BUFFER: usize = 256;
+
+/// How long connecting to the socket or writing one event may take before the consumer is
+/// considered stalled
+#[cfg(unix)]
+pub const EVENT_WRITE_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(1);
+
+/// One completed frame, sent as a single JSON line
+#[derive(Serialize, Debug, Clone)]
+pub struct FrameEvent {
+    /// Frame time in RFC 3339
+    pub timestamp: String,
+    pub url: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bytes: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Attempts after the first, absent when the first one settled it
+    #[serde(skip_serializing_if = "is_zero")]
+    pub retries: u32,
+}
+
+fn is_zero(n: &u32) -> bool {
+    *n == 0
+}
+
+/// Spawns a task that writes every event it receives as JSONL to the Unix socket at `path`.
+///
+/// Delivery is best effort: if nothing is listening, the task retries the connection on each
+/// event and drops events that can't be written, so a missing consumer never stalls downloads.
+/// A consumer that stops reading is given up on for the rest of the run once a connect or write
+/// takes longer than [`EVENT_WRITE_TIMEOUT`], so draining the task at the end can't hang either.
+/// The task finishes once every sender is dropped, returning how many events were delivered.
+#[cfg(unix)]
+pub fn spawn_socket_writer(path: PathBuf) -> (mpsc::Sender<FrameEvent>, JoinHandle<usize>) {
+    let (tx, mut rx) = mpsc::channel::<FrameEvent>(EVENT_BUFFER);
+    let handle = tokio::spawn(async move {
+        let mut stream = None;
+        let mut delivered = 0;
+        let mut stalled = false;
+        while let Some(event) = rx.recv().await {
+            if stalled {
+                continue;
+            }
+            if stream.is_none() {
+                match tokio::time::timeout(
+                    EVENT_WRITE_TIMEOUT,
+                    tokio::net::UnixStream::connect(&path),
+                )
+                .await
+                {
+                    Ok(connected) => stream = connected.ok(),
+                    Err(_) => stalled = true,
+                }
+            }
+            let Some(socket) = stream.as_mut() else {
+                if stalled {
+                    tracing::warn!(
+                        "Connecting to {} timed out, dropping further events",
+                        path.display()
+                    );
+                }
+                continue;
+            };
+            let mut line = serde_json::to_vec(&event).expect("events always serialize");
+            line.push(b'\n');
+            match tokio::time::timeout(EVENT_WRITE_TIMEOUT, socket.write_all(&line)).await {
+                Ok(Ok(())) => delivered += 1,
+                Ok(Err(_)) => stream = None,
+                Err(_) => {
+                    tracing::warn!(
+                        "{} stopped reading events, dropping further events",
+                        path.display()
+                    );
+                    stream = None;
+                    stalled = true;
+                }
+            }
+        }
+        delivered
+    });
+    (tx, handle)
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use tokio::io::AsyncBufReadExt;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn writes_jsonl_to_socket() -> std::io::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("events.sock");
+        let listener = tokio::net::UnixListener::bind(&path)?;
+        let (tx, writer) = spawn_socket_writer(path.clone());
+        tx.send(FrameEvent {
+            timestamp: "2024-11-30T08:30:00+00:00".to_string(),
+            url: "https://example.com/a.jpg".to_string(),
+            path: Some("a.jpg".to_string()),
+            bytes: Some(3),
+            sha256: None,
+            error: None,
+            retries: 0,
+        })
+        .await
+        .unwrap();
+        drop(tx);
+
+        let (socket, _) = listener.accept().await?;
+        let mut line = String::new();
+        tokio::io::BufReader::new(socket)
+            .read_line(&mut line)
+            .await?;
+        assert_eq!(1, writer.await.unwrap());
+        assert_eq!(
+            "{\"timestamp\":\"2024-11-30T08:30:00+00:00\",\"url\":\"https://example.com/a.jpg\",\"path\":\"a.jpg\",\"bytes\":3}\n",
+            line
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn gives_up_on_a_consumer_that_stops_reading() -> std::io::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("events.sock");
+        let listener = tokio::net::UnixListener::bind(&path)?;
+        let (tx, writer) = spawn_socket_writer(path.clone());
+        // Far more than the socket buffers, so the first write can only finish if it's read
+        let event = FrameEvent {
+            timestamp: "2024-11-30T08:30:00+00:00".to_string(),
+            url: "x".repeat(16 << 20),
+            path: None,
+            bytes: None,
+            sha256: None,
+            error: None,
+            retries: 0,
+        };
+        for _ in 0..3 {
+            tx.send(event.clone()).await.unwrap();
+        }
+        drop(tx);
+
+        let (_socket, _) = listener.accept().await?;
+        let delivered = tokio::time::timeout(EVENT_WRITE_TIMEOUT * 3, writer)
+            .await
+            .expect("a stalled consumer is given up on after one timeout")
+            .unwrap();
+        assert_eq!(0, delivered);
+        Ok(())
+    }
+}
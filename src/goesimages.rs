@@ -1,26 +1,405 @@
-use chrono::Utc;
+use std::str::FromStr;
+
+use chrono::{DateTime, NaiveDate, Utc};
+use clap::ValueEnum;
 use url::Url;
 
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
 pub enum Sat {
+    /// Whichever satellite was operating as GOES-East at the time of each frame
+    #[value(name = "east")]
     GoesEast,
+    #[value(name = "goes16")]
+    Goes16,
+    #[value(name = "goes19")]
+    Goes19,
+    #[value(name = "west", alias = "goes18")]
     GoesWest,
 }
 
+/// First day GOES-19 took over as GOES-East. GOES-16 imagery stops being published after it
+pub fn goes19_handover() -> DateTime<Utc> {
+    NaiveDate::from_ymd_opt(2025, 4, 7).unwrap().and_hms_opt(0, 0, 0).unwrap().and_utc()
+}
+
 impl Sat {
+    /// CDN path of the satellite. [`Sat::GoesEast`] gives the current GOES-East; use
+    /// [`Sat::operational_at`] first for frames from before the handover
     pub const fn url_frag(&self) -> &'static str {
         match self {
-            Sat::GoesEast => "GOES16",
+            Sat::Goes16 => "GOES16",
+            Sat::GoesEast | Sat::Goes19 => "GOES19",
             Sat::GoesWest => "GOES18",
         }
     }
+
+    /// The concrete satellite imaging at `time`, resolving [`Sat::GoesEast`] by the handover date
+    pub fn operational_at(self, time: &DateTime<Utc>) -> Sat {
+        match self {
+            Sat::GoesEast if *time < goes19_handover() => Sat::Goes16,
+            Sat::GoesEast => Sat::Goes19,
+            sat => sat,
+        }
+    }
+
+    /// Longitude in degrees (east positive) of the point on the equator the satellite sits above
+    pub const fn sub_longitude(&self) -> f64 {
+        match self {
+            Sat::GoesEast | Sat::Goes16 | Sat::Goes19 => -75.2,
+            Sat::GoesWest => -137.2,
+        }
+    }
+}
+
+/// Parses a [`ValueEnum`] case-insensitively, with an error listing every accepted name
+fn parse_value_enum<T: ValueEnum>(kind: &str, s: &str) -> Result<T, String> {
+    T::from_str(s, true).map_err(|_| {
+        let valid: Vec<String> = T::value_variants()
+            .iter()
+            .filter_map(|v| v.to_possible_value())
+            .map(|v| v.get_name().to_string())
+            .collect();
+        format!("Unknown {kind} '{s}', expected one of: {}", valid.join(", "))
+    })
+}
+
+impl FromStr for Sat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_value_enum("satellite", s)
+    }
+}
+
+/// The area of the disk an image covers
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Sector {
+    #[value(name = "fd", alias = "full-disk")]
+    FullDisk,
+    #[value(name = "conus")]
+    Conus,
+    #[value(name = "meso1", alias = "m1")]
+    Meso1,
+    #[value(name = "meso2", alias = "m2")]
+    Meso2,
+}
+
+impl Sector {
+    /// The directory under `ABI/`
+    pub const fn path_frag(&self) -> &'static str {
+        match self {
+            Sector::FullDisk => "FD",
+            Sector::Conus => "CONUS",
+            Sector::Meso1 => "MESO/M1",
+            Sector::Meso2 => "MESO/M2",
+        }
+    }
+
+    /// The sector token in frame file names
+    pub const fn url_frag(&self) -> &'static str {
+        match self {
+            Sector::FullDisk => "FD",
+            Sector::Conus => "CONUS",
+            Sector::Meso1 => "M1",
+            Sector::Meso2 => "M2",
+        }
+    }
+
+    /// How often a new frame is published, in minutes
+    pub const fn cadence_minutes(&self) -> i64 {
+        match self {
+            Sector::FullDisk => 10,
+            Sector::Conus => 5,
+            Sector::Meso1 | Sector::Meso2 => 1,
+        }
+    }
+
+    /// The shortest stride, and the unit every stride and range duration has to be a multiple
+    /// of, since no frames exist between two publications
+    pub const fn min_stride_minutes(&self) -> i64 {
+        self.cadence_minutes()
+    }
+}
+
+impl FromStr for Sector {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_value_enum("sector", s)
+    }
+}
+
+/// The size tiers NOAA publishes frames at, from smallest to largest. The pixel sizes differ
+/// per [`Sector`] (the aliases are the full disk ones) and anything else just 404s
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Resolution {
+    #[value(name = "thumbnail", alias = "339x339")]
+    Thumbnail,
+    #[value(name = "low", alias = "678x678")]
+    Low,
+    #[value(name = "medium", alias = "1808x1808")]
+    Medium,
+    #[value(name = "high", alias = "5424x5424")]
+    High,
+    #[value(name = "full", alias = "10848x10848")]
+    Full,
+    #[value(name = "max", alias = "21696x21696")]
+    Max,
+}
+
+impl Resolution {
+    /// `(width, height)` of this tier in `sector`, if NOAA publishes `product` at it.
+    ///
+    /// | Tier      | fd          | conus     | meso1/meso2 |
+    /// |-----------|-------------|-----------|-------------|
+    /// | thumbnail | 339x339     | 416x250   | 250x250     |
+    /// | low       | 678x678     | 625x375   | 500x500     |
+    /// | medium    | 1808x1808   | 1250x750  | 1000x1000   |
+    /// | high      | 5424x5424   | 2500x1500 | 2000x2000   |
+    /// | full      | 10848x10848 | 5000x3000 | -           |
+    /// | max       | 21696x21696 | 10000x6000| -           |
+    ///
+    /// Only band 2 (the 0.5km band) is published at `max`
+    pub fn dimensions(&self, sector: &Sector, product: &Product) -> Option<(u32, u32)> {
+        if *self == Resolution::Max && *product != Product::Band02 {
+            return None;
+        }
+        match sector {
+            Sector::FullDisk => {
+                let side = match self {
+                    Resolution::Thumbnail => 339,
+                    Resolution::Low => 678,
+                    Resolution::Medium => 1808,
+                    Resolution::High => 5424,
+                    Resolution::Full => 10848,
+                    Resolution::Max => 21696,
+                };
+                Some((side, side))
+            }
+            Sector::Conus => Some(match self {
+                Resolution::Thumbnail => (416, 250),
+                Resolution::Low => (625, 375),
+                Resolution::Medium => (1250, 750),
+                Resolution::High => (2500, 1500),
+                Resolution::Full => (5000, 3000),
+                Resolution::Max => (10000, 6000),
+            }),
+            Sector::Meso1 | Sector::Meso2 => {
+                let side = match self {
+                    Resolution::Thumbnail => 250,
+                    Resolution::Low => 500,
+                    Resolution::Medium => 1000,
+                    Resolution::High => 2000,
+                    Resolution::Full | Resolution::Max => return None,
+                };
+                Some((side, side))
+            }
+        }
+    }
+}
+
+impl FromStr for Resolution {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_value_enum("resolution", s)
+    }
+}
+
+/// A [`Resolution`] tier, or the largest or smallest one a sector and product are published at
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ResolutionChoice {
+    Exact(Resolution),
+    Best,
+    Worst,
+}
+
+impl ResolutionChoice {
+    /// The concrete tier, looked up in the [`Resolution::dimensions`] table
+    pub fn resolve(&self, sector: &Sector, product: &Product) -> Resolution {
+        let mut published = Resolution::value_variants().iter().copied().filter(|r| r.dimensions(sector, product).is_some());
+        let resolved = match self {
+            ResolutionChoice::Exact(resolution) => return *resolution,
+            ResolutionChoice::Best => published.next_back(),
+            ResolutionChoice::Worst => published.next(),
+        };
+        resolved.expect("every sector publishes every product at thumbnail size")
+    }
+}
+
+impl ValueEnum for ResolutionChoice {
+    fn value_variants<'a>() -> &'a [Self] {
+        &[
+            ResolutionChoice::Best,
+            ResolutionChoice::Worst,
+            ResolutionChoice::Exact(Resolution::Thumbnail),
+            ResolutionChoice::Exact(Resolution::Low),
+            ResolutionChoice::Exact(Resolution::Medium),
+            ResolutionChoice::Exact(Resolution::High),
+            ResolutionChoice::Exact(Resolution::Full),
+            ResolutionChoice::Exact(Resolution::Max),
+        ]
+    }
+
+    fn to_possible_value(&self) -> Option<clap::builder::PossibleValue> {
+        match self {
+            ResolutionChoice::Exact(resolution) => resolution.to_possible_value(),
+            ResolutionChoice::Best => Some(clap::builder::PossibleValue::new("best")),
+            ResolutionChoice::Worst => Some(clap::builder::PossibleValue::new("worst")),
+        }
+    }
+}
+
+impl FromStr for ResolutionChoice {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_value_enum("resolution", s)
+    }
+}
+
+/// ABI imagery NOAA publishes: the GEOCOLOR composite, a few other RGB composites and the 16
+/// individual bands.
+///
+/// Every product comes in every [`Resolution`] its sector has up to [`Resolution::Full`].
+/// Band 2 (0.64µm red, the 0.5km band) is the only one also published at [`Resolution::Max`]
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Product {
+    #[value(name = "geocolor")]
+    GeoColor,
+    #[value(name = "air-mass", alias = "AirMass")]
+    AirMass,
+    #[value(name = "sandwich")]
+    Sandwich,
+    #[value(name = "day-cloud-phase", alias = "DayCloudPhase")]
+    DayCloudPhase,
+    #[value(name = "fire-temperature", alias = "FireTemperature")]
+    FireTemperature,
+    #[value(name = "dust")]
+    Dust,
+    #[value(name = "band01", alias = "01")]
+    Band01,
+    #[value(name = "band02", alias = "02")]
+    Band02,
+    #[value(name = "band03", alias = "03")]
+    Band03,
+    #[value(name = "band04", alias = "04")]
+    Band04,
+    #[value(name = "band05", alias = "05")]
+    Band05,
+    #[value(name = "band06", alias = "06")]
+    Band06,
+    #[value(name = "band07", alias = "07")]
+    Band07,
+    #[value(name = "band08", alias = "08")]
+    Band08,
+    #[value(name = "band09", alias = "09")]
+    Band09,
+    #[value(name = "band10", alias = "10")]
+    Band10,
+    #[value(name = "band11", alias = "11")]
+    Band11,
+    #[value(name = "band12", alias = "12")]
+    Band12,
+    #[value(name = "band13", alias = "13")]
+    Band13,
+    #[value(name = "band14", alias = "14")]
+    Band14,
+    #[value(name = "band15", alias = "15")]
+    Band15,
+    #[value(name = "band16", alias = "16")]
+    Band16,
+}
+
+impl Product {
+    /// The token used for both the directory and the file name
+    pub const fn url_frag(&self) -> &'static str {
+        match self {
+            Product::GeoColor => "GEOCOLOR",
+            Product::AirMass => "AirMass",
+            Product::Sandwich => "Sandwich",
+            Product::DayCloudPhase => "DayCloudPhase",
+            Product::FireTemperature => "FireTemperature",
+            Product::Dust => "Dust",
+            Product::Band01 => "01",
+            Product::Band02 => "02",
+            Product::Band03 => "03",
+            Product::Band04 => "04",
+            Product::Band05 => "05",
+            Product::Band06 => "06",
+            Product::Band07 => "07",
+            Product::Band08 => "08",
+            Product::Band09 => "09",
+            Product::Band10 => "10",
+            Product::Band11 => "11",
+            Product::Band12 => "12",
+            Product::Band13 => "13",
+            Product::Band14 => "14",
+            Product::Band15 => "15",
+            Product::Band16 => "16",
+        }
+    }
+}
+
+impl FromStr for Product {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_value_enum("product", s)
+    }
 }
 
 
 // From https://cdn.star.nesdis.noaa.gov/GOES16/ABI/FD/GEOCOLOR/20243350830_GOES16-ABI-FD-GEOCOLOR-1808x1808.jpg
-const CDN_PREFIX: &'static str = "cdn.star.nesdis.noaa.gov";
-pub fn construct_image_url(sat: &Sat, time: &chrono::DateTime<Utc>) -> Result<Url, Box<dyn std::error::Error>> {
+/// NOAA's CDN, which every path below is relative to unless a mirror is configured
+pub const DEFAULT_BASE_URL: &str = "https://cdn.star.nesdis.noaa.gov";
+
+pub fn default_base_url() -> Url {
+    Url::parse(DEFAULT_BASE_URL).expect("the default base URL is valid")
+}
+
+/// Parses a mirror's base URL, which has to be http(s) with a host. Any path is kept, so a
+/// mirror can serve the CDN's layout from a subdirectory
+pub fn parse_base_url(s: &str) -> Result<Url, String> {
+    let url = Url::parse(s).map_err(|e| format!("Invalid base URL '{s}': {e}"))?;
+    if !matches!(url.scheme(), "http" | "https") || url.host_str().is_none() {
+        return Err(format!("Base URL '{s}' has to be an http:// or https:// URL with a host"));
+    }
+    Ok(url)
+}
+/// The `(width, height)` of `product` in `sector` at `resolution`, or an error naming the
+/// combination when NOAA doesn't publish it
+pub fn published_dimensions(sector: &Sector, product: &Product, resolution: &Resolution) -> Result<(u32, u32), String> {
+    resolution.dimensions(sector, product).ok_or_else(|| {
+        let name = |v: Option<clap::builder::PossibleValue>| v.map(|v| v.get_name().to_string()).unwrap_or_default();
+        format!("{} isn't published at {} resolution in the {} sector", name(product.to_possible_value()), name(resolution.to_possible_value()), name(sector.to_possible_value()))
+    })
+}
+
+/// Fails without building a URL for a combination NOAA doesn't publish
+pub fn construct_image_url(sat: &Sat, sector: &Sector, product: &Product, resolution: &Resolution, time: &chrono::DateTime<Utc>) -> Result<Url, Box<dyn std::error::Error>> {
+    construct_image_url_on(&default_base_url(), sat, sector, product, resolution, time)
+}
+
+/// [`construct_image_url`] on a mirror of the CDN at `base`
+pub fn construct_image_url_on(base: &Url, sat: &Sat, sector: &Sector, product: &Product, resolution: &Resolution, time: &chrono::DateTime<Utc>) -> Result<Url, Box<dyn std::error::Error>> {
+    let (width, height) = published_dimensions(sector, product, resolution)?;
     let datetime = time.format("%Y%j%H%M");
-    Ok(Url::parse(&format!("https://{CDN_PREFIX}/{sat_url_frag}/ABI/FD/GEOCOLOR/{datetime}_{sat_url_frag}-ABI-FD-GEOCOLOR-1808x1808.jpg", sat_url_frag = sat.url_frag()))?)
+    let base = base.as_str().trim_end_matches('/');
+    Ok(Url::parse(&format!("{base}/{sat_url_frag}/ABI/{sector_path}/{product}/{datetime}_{sat_url_frag}-ABI-{sector}-{product}-{width}x{height}.jpg", sat_url_frag = sat.operational_at(time).url_frag(), sector_path = sector.path_frag(), sector = sector.url_frag(), product = product.url_frag()))?)
+}
+
+// From https://cdn.star.nesdis.noaa.gov/GOES16/ABI/FD/GEOCOLOR/GOES16-FD-GEOCOLOR-625x625.gif
+/// NOAA's own pre-made loop of the most recent full disk GEOCOLOR frames, regenerated as new
+/// frames publish
+pub fn construct_animation_url(sat: &Sat) -> Result<Url, Box<dyn std::error::Error>> {
+    construct_animation_url_on(&default_base_url(), sat)
+}
+
+/// [`construct_animation_url`] on a mirror of the CDN at `base`
+pub fn construct_animation_url_on(base: &Url, sat: &Sat) -> Result<Url, Box<dyn std::error::Error>> {
+    let base = base.as_str().trim_end_matches('/');
+    Ok(Url::parse(&format!("{base}/{sat_url_frag}/ABI/FD/GEOCOLOR/{sat_url_frag}-FD-GEOCOLOR-625x625.gif", sat_url_frag = sat.url_frag()))?)
 }
 
 
@@ -33,8 +412,110 @@ mod tests {
     #[test]
     fn parse_success() -> Result<(), Box<dyn std::error::Error>> {
         let datetime = chrono::Utc.with_ymd_and_hms(2024, 11, 30, 8, 30, 00).unwrap();
-        let result = construct_image_url(&Sat::GoesEast, &datetime)?;
+        let result = construct_image_url(&Sat::GoesEast, &Sector::FullDisk, &Product::GeoColor, &Resolution::Medium, &datetime)?;
         assert_eq!("https://cdn.star.nesdis.noaa.gov/GOES16/ABI/FD/GEOCOLOR/20243350830_GOES16-ABI-FD-GEOCOLOR-1808x1808.jpg", result.as_str());
         Ok(())
     }
+
+    #[test]
+    fn resolution_urls() -> Result<(), Box<dyn std::error::Error>> {
+        let datetime = chrono::Utc.with_ymd_and_hms(2024, 11, 30, 8, 30, 00).unwrap();
+        let result = construct_image_url(&Sat::GoesEast, &Sector::FullDisk, &Product::GeoColor, &Resolution::Thumbnail, &datetime)?;
+        assert_eq!("https://cdn.star.nesdis.noaa.gov/GOES16/ABI/FD/GEOCOLOR/20243350830_GOES16-ABI-FD-GEOCOLOR-339x339.jpg", result.as_str());
+        let result = construct_image_url(&Sat::GoesEast, &Sector::FullDisk, &Product::GeoColor, &Resolution::Full, &datetime)?;
+        assert_eq!("https://cdn.star.nesdis.noaa.gov/GOES16/ABI/FD/GEOCOLOR/20243350830_GOES16-ABI-FD-GEOCOLOR-10848x10848.jpg", result.as_str());
+        Ok(())
+    }
+
+    #[test]
+    fn product_urls() -> Result<(), Box<dyn std::error::Error>> {
+        let datetime = chrono::Utc.with_ymd_and_hms(2024, 11, 30, 8, 30, 00).unwrap();
+        let result = construct_image_url(&Sat::GoesEast, &Sector::FullDisk, &Product::Band02, &Resolution::Max, &datetime)?;
+        assert_eq!("https://cdn.star.nesdis.noaa.gov/GOES16/ABI/FD/02/20243350830_GOES16-ABI-FD-02-21696x21696.jpg", result.as_str());
+        let result = construct_image_url(&Sat::GoesWest, &Sector::FullDisk, &Product::AirMass, &Resolution::Medium, &datetime)?;
+        assert_eq!("https://cdn.star.nesdis.noaa.gov/GOES18/ABI/FD/AirMass/20243350830_GOES18-ABI-FD-AirMass-1808x1808.jpg", result.as_str());
+        assert!(construct_image_url(&Sat::GoesEast, &Sector::FullDisk, &Product::GeoColor, &Resolution::Max, &datetime).is_err());
+        assert_eq!(Ok(Product::DayCloudPhase), "DayCloudPhase".parse());
+        assert_eq!(Ok(Product::Band13), "13".parse());
+        Ok(())
+    }
+
+    #[test]
+    fn sector_urls() -> Result<(), Box<dyn std::error::Error>> {
+        let datetime = chrono::Utc.with_ymd_and_hms(2024, 11, 30, 8, 31, 00).unwrap();
+        let result = construct_image_url(&Sat::GoesEast, &Sector::Conus, &Product::GeoColor, &Resolution::Medium, &datetime)?;
+        assert_eq!("https://cdn.star.nesdis.noaa.gov/GOES16/ABI/CONUS/GEOCOLOR/20243350831_GOES16-ABI-CONUS-GEOCOLOR-1250x750.jpg", result.as_str());
+        let result = construct_image_url(&Sat::GoesEast, &Sector::Meso2, &Product::Sandwich, &Resolution::High, &datetime)?;
+        assert_eq!("https://cdn.star.nesdis.noaa.gov/GOES16/ABI/MESO/M2/Sandwich/20243350831_GOES16-ABI-M2-Sandwich-2000x2000.jpg", result.as_str());
+        assert_eq!(
+            Err("geocolor isn't published at full resolution in the meso1 sector".to_string()),
+            published_dimensions(&Sector::Meso1, &Product::GeoColor, &Resolution::Full)
+        );
+        assert_eq!(Ok(Sector::Meso1), "M1".parse());
+        Ok(())
+    }
+
+    #[test]
+    fn resolution_from_str() {
+        assert_eq!(Ok(Resolution::High), "HIGH".parse());
+        assert_eq!(Ok(Resolution::Low), "678x678".parse());
+        assert_eq!(Some((5424, 5424)), Resolution::High.dimensions(&Sector::FullDisk, &Product::GeoColor));
+        assert_eq!(
+            Err("Unknown resolution '1000x1000', expected one of: thumbnail, low, medium, high, full, max".to_string()),
+            "1000x1000".parse::<Resolution>()
+        );
+    }
+
+    #[test]
+    fn best_and_worst_follow_the_sector() {
+        let resolve = |choice: &str, sector, product| choice.parse::<ResolutionChoice>().unwrap().resolve(&sector, &product);
+        assert_eq!(Resolution::Full, resolve("best", Sector::FullDisk, Product::GeoColor));
+        assert_eq!(Resolution::Max, resolve("best", Sector::Conus, Product::Band02));
+        assert_eq!(Resolution::High, resolve("BEST", Sector::Meso1, Product::Band02));
+        assert_eq!(Resolution::Thumbnail, resolve("worst", Sector::Meso2, Product::AirMass));
+        assert_eq!(Resolution::Low, resolve("678x678", Sector::Conus, Product::GeoColor));
+    }
+
+    #[test]
+    fn goes19_urls() -> Result<(), Box<dyn std::error::Error>> {
+        let before = chrono::Utc.with_ymd_and_hms(2025, 4, 6, 23, 50, 00).unwrap();
+        let after = chrono::Utc.with_ymd_and_hms(2025, 4, 7, 0, 0, 00).unwrap();
+        let url = |sat, time| construct_image_url(&sat, &Sector::FullDisk, &Product::GeoColor, &Resolution::Medium, &time);
+        assert_eq!("https://cdn.star.nesdis.noaa.gov/GOES19/ABI/FD/GEOCOLOR/20250970000_GOES19-ABI-FD-GEOCOLOR-1808x1808.jpg", url(Sat::Goes19, after)?.as_str());
+        assert_eq!("https://cdn.star.nesdis.noaa.gov/GOES16/ABI/FD/GEOCOLOR/20250962350_GOES16-ABI-FD-GEOCOLOR-1808x1808.jpg", url(Sat::GoesEast, before)?.as_str());
+        assert_eq!(url(Sat::Goes19, after)?, url(Sat::GoesEast, after)?);
+        assert_eq!("GOES16", url(Sat::Goes16, after)?.path_segments().unwrap().next().unwrap());
+        Ok(())
+    }
+
+    #[test]
+    fn mirror_urls() -> Result<(), Box<dyn std::error::Error>> {
+        let datetime = chrono::Utc.with_ymd_and_hms(2024, 11, 30, 8, 30, 00).unwrap();
+        for base in ["http://127.0.0.1:8080/noaa", "http://127.0.0.1:8080/noaa/"] {
+            let result = construct_image_url_on(&parse_base_url(base)?, &Sat::GoesEast, &Sector::FullDisk, &Product::GeoColor, &Resolution::Medium, &datetime)?;
+            assert_eq!("http://127.0.0.1:8080/noaa/GOES16/ABI/FD/GEOCOLOR/20243350830_GOES16-ABI-FD-GEOCOLOR-1808x1808.jpg", result.as_str());
+        }
+        assert!(parse_base_url("cdn.example.com").is_err());
+        assert!(parse_base_url("ftp://cdn.example.com").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn animation_url() -> Result<(), Box<dyn std::error::Error>> {
+        let result = construct_animation_url(&Sat::GoesWest)?;
+        assert_eq!("https://cdn.star.nesdis.noaa.gov/GOES18/ABI/FD/GEOCOLOR/GOES18-FD-GEOCOLOR-625x625.gif", result.as_str());
+        Ok(())
+    }
+
+    #[test]
+    fn sat_from_str() {
+        assert_eq!(Ok(Sat::GoesEast), "east".parse());
+        assert_eq!(Ok(Sat::GoesWest), "WEST".parse());
+        assert_eq!(Ok(Sat::GoesWest), "goes18".parse());
+        assert_eq!(Ok(Sat::Goes19), "GOES19".parse());
+        assert_eq!(
+            Err("Unknown satellite 'north', expected one of: east, goes16, goes19, west".to_string()),
+            "north".parse::<Sat>()
+        );
+    }
 }
\ No newline at end of file
@@ -0,0 +1,106 @@
+use std::path::Path;
+
+use rusqlite::{params, Connection};
+
+/// One downloaded frame as stored in the index database
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IndexRecord {
+    /// Frame time in RFC 3339
+    pub timestamp: String,
+    pub sat: String,
+    pub product: String,
+    pub sector: String,
+    pub resolution: String,
+    pub path: String,
+    pub bytes: u64,
+    pub sha256: String,
+    pub etag: Option<String>,
+}
+
+/// A SQLite database of downloaded frames keyed by (timestamp, sat, product, sector, resolution).
+///
+/// SQLite allows a single writer at a time, so callers should funnel every insert through one
+/// owner of this handle.
+pub struct FrameIndex {
+    conn: Connection,
+}
+
+impl FrameIndex {
+    /// Opens or creates the database at `path`, creating the schema if it's missing
+    pub fn open(path: &Path) -> rusqlite::Result<FrameIndex> {
+        let conn = Connection::open(path)?;
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS frames (
+                timestamp TEXT NOT NULL,
+                sat TEXT NOT NULL,
+                product TEXT NOT NULL,
+                sector TEXT NOT NULL,
+                resolution TEXT NOT NULL,
+                path TEXT NOT NULL,
+                bytes INTEGER NOT NULL,
+                sha256 TEXT NOT NULL,
+                etag TEXT,
+                PRIMARY KEY (timestamp, sat, product, sector, resolution)
+            );",
+        )?;
+        Ok(FrameIndex { conn })
+    }
+
+    /// Inserts the frame, replacing the stored details if it was indexed before
+    pub fn upsert(&self, record: &IndexRecord) -> rusqlite::Result<()> {
+        self.conn.execute(
+            "INSERT INTO frames
+                (timestamp, sat, product, sector, resolution, path, bytes, sha256, etag)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)
+            ON CONFLICT (timestamp, sat, product, sector, resolution) DO UPDATE SET
+                path = excluded.path,
+                bytes = excluded.bytes,
+                sha256 = excluded.sha256,
+                etag = excluded.etag",
+            params![
+                record.timestamp,
+                record.sat,
+                record.product,
+                record.sector,
+                record.resolution,
+                record.path,
+                record.bytes as i64,
+                record.sha256,
+                record.etag,
+            ],
+        )?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn upsert_replaces_by_natural_key() -> rusqlite::Result<()> {
+        let index = FrameIndex::open(Path::new(":memory:"))?;
+        let mut record = IndexRecord {
+            timestamp: "2024-11-30T08:30:00+00:00".to_string(),
+            sat: "GOES16".to_string(),
+            product: "GEOCOLOR".to_string(),
+            sector: "FD".to_string(),
+            resolution: "1808x1808".to_string(),
+            path: "a.jpg".to_string(),
+            bytes: 1,
+            sha256: "00".to_string(),
+            etag: None,
+        };
+        index.upsert(&record)?;
+        record.path = "b.jpg".to_string();
+        index.upsert(&record)?;
+        let (count, path): (u32, String) =
+            index
+                .conn
+                .query_row("SELECT COUNT(*), MAX(path) FROM frames", [], |row| {
+                    Ok((row.get(0)?, row.get(1)?))
+                })?;
+        assert_eq!((1, "b.jpg".to_string()), (count, path));
+        Ok(())
+    }
+}
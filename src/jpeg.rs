@@ -0,0 +1,72 @@
+/// Reads the `(width, height)` of a JPEG from its frame header without decoding any image data.
+///
+/// Returns `None` if the data isn't a JPEG or ends before a start-of-frame segment.
+pub fn dimensions(data: &[u8]) -> Option<(u32, u32)> {
+    if !data.starts_with(&[0xFF, 0xD8]) {
+        return None;
+    }
+    let mut pos = 2;
+    loop {
+        // Markers may be padded with any number of 0xFF fill bytes
+        while *data.get(pos)? == 0xFF && *data.get(pos + 1)? == 0xFF {
+            pos += 1;
+        }
+        if *data.get(pos)? != 0xFF {
+            return None;
+        }
+        let marker = *data.get(pos + 1)?;
+        pos += 2;
+        match marker {
+            // Standalone markers without a length
+            0x01 | 0xD0..=0xD7 => continue,
+            // Start of scan: entropy coded data follows, so there was no frame header
+            0xDA | 0xD9 => return None,
+            _ => {}
+        }
+        let length = u16::from_be_bytes([*data.get(pos)?, *data.get(pos + 1)?]) as usize;
+        // Every SOFn except DHT (C4), JPG (C8) and DAC (CC)
+        if matches!(marker, 0xC0..=0xCF) && !matches!(marker, 0xC4 | 0xC8 | 0xCC) {
+            let height = u16::from_be_bytes([*data.get(pos + 3)?, *data.get(pos + 4)?]);
+            let width = u16::from_be_bytes([*data.get(pos + 5)?, *data.get(pos + 6)?]);
+            return Some((width as u32, height as u32));
+        }
+        pos += length;
+    }
+}
+
+/// Why `data` isn't a whole JPEG file, judged by its start-of-image and end-of-image markers:
+/// an HTML error page has neither and a truncated download is missing the last
+pub fn incomplete(data: &[u8]) -> Option<&'static str> {
+    if !data.starts_with(&[0xFF, 0xD8]) {
+        Some("no start-of-image marker")
+    } else if data.len() < 4 || !data.ends_with(&[0xFF, 0xD9]) {
+        Some("no end-of-image marker, it was cut short")
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn complete_needs_both_markers() {
+        assert_eq!(None, incomplete(&[0xFF, 0xD8, 0x00, 0xFF, 0xD9]));
+        assert!(incomplete(b"<html>").is_some());
+        assert!(incomplete(&[0xFF, 0xD8, 0xFF, 0xE0, 0x00]).is_some());
+        assert!(incomplete(&[0xFF, 0xD8, 0xD9]).is_some());
+    }
+
+    #[test]
+    fn reads_sof_after_other_segments() {
+        let data = [
+            0xFF, 0xD8, // SOI
+            0xFF, 0xE0, 0x00, 0x04, 0x4A, 0x46, // APP0, truncated payload
+            0xFF, 0xC0, 0x00, 0x11, 0x08, 0x07, 0x10, 0x03, 0x88, // SOF0 1808 high, 904 wide
+        ];
+        assert_eq!(Some((904, 1808)), dimensions(&data));
+        assert_eq!(None, dimensions(b"<html>"));
+        assert_eq!(None, dimensions(&data[..12]));
+    }
+}
@@ -1 +1,15 @@
-pub mod goesimages;
\ No newline at end of file
+#[cfg(feature = "imaging")]
+pub mod animation;
+pub mod checksum;
+#[cfg(feature = "imaging")]
+pub mod contact_sheet;
+pub mod download;
+pub mod events;
+pub mod goesimages;
+pub mod index;
+pub mod jpeg;
+pub mod manifest;
+pub mod plan;
+pub mod solar;
+#[cfg(feature = "imaging")]
+pub mod stack;
\ No newline at end of file
@@ -1,21 +1,67 @@
-use chrono::{DateTime, Duration, TimeDelta, Timelike, Utc};
-use clap::{ArgGroup, Parser};
-use goesdown::goesimages;
-use reqwest::Client;
-use std::{path::Path, str::FromStr, sync::Arc};
-use tokio::sync::Semaphore;
+use chrono::{
+    DateTime, Duration, Local, NaiveDate, NaiveDateTime, TimeDelta, TimeZone, Timelike, Utc,
+};
+use chrono_tz::Tz;
+use clap::{parser::ValueSource, ArgGroup, ArgMatches, CommandFactory, FromArgMatches, Parser};
+use futures::StreamExt;
+#[cfg(feature = "imaging")]
+use goesdown::animation;
+use goesdown::{
+    checksum,
+    download::{
+        self, format_log_time, parse_duration, parse_span, send_with_reconnect, url_file_name,
+        DownloadError, FetchConfig, FinishedFrame, RateLimiter, SavedImage, TimeoutScaling,
+        NEAREST_MAX_STEPS, RETRY_BASE_DELAY,
+    },
+    events::FrameEvent,
+    goesimages,
+    index::{FrameIndex, IndexRecord},
+    manifest::{Manifest, ManifestEntry, MANIFEST_FILE},
+    plan::{self, format_output_time, DownloadPlan, NameTemplate, PlanOptions, PlannedFrame},
+    solar,
+};
+use indicatif::{HumanBytes, ProgressBar, ProgressDrawTarget, ProgressStyle};
+use reqwest::{Client, StatusCode};
+use serde::Deserialize;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs::File,
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+};
+use tokio::{sync::mpsc, task::JoinHandle};
+use tracing::{error, info, warn, Level};
 
 /// CLI tool to retrieve images from an API with a specified range
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
 #[clap(group(
     ArgGroup::new("time")
-        .required(true)
+        .required(false)
         .multiple(false)
-        .args(&["start", "ago"]),
+        .args(&["start", "ago", "since_manifest", "noaa_animation"]),
+))]
+#[clap(group(
+    ArgGroup::new("existing")
+        .required(false)
+        .multiple(false)
+        .args(&["resume", "overwrite"]),
+))]
+#[clap(group(
+    ArgGroup::new("range_end")
+        .required(false)
+        .multiple(false)
+        .args(&["duration", "end"]),
 ))]
 struct Cli {
-    /// Start time for the image range in ISO 8601 format (e.g., 2024-11-30T12:00:00Z)
+    /// Start time for the image range: RFC 3339 with an offset (e.g., 2024-11-30T12:00:00Z), a
+    /// date and time without one (2024-11-30T12:00:00 or "2024-11-30 12:00") or a date
+    /// (2024-11-30, meaning midnight). Times without an offset are in --timezone. Rounded down
+    /// to the sector's publication cadence
     #[arg(long, group = "time")]
     start: Option<String>,
 
@@ -23,47 +69,571 @@ struct Cli {
     #[arg(long, group = "time")]
     ago: Option<String>,
 
+    /// Continue from an earlier run: start one stride after the newest frame the manifest.json
+    /// at PATH records as saved, and download up to the latest frame
+    #[arg(long, value_name = "PATH", group = "time")]
+    since_manifest: Option<String>,
+
+    /// Instead of downloading a range of frames, save NOAA's own pre-made animated GIF of the
+    /// latest full disk GEOCOLOR frames into the root directory
+    #[arg(long, group = "time")]
+    noaa_animation: bool,
+
     /// Duration of the image range in a format like "2d12h20m" (optional; defaults to now - start)
-    #[arg(short, long)]
+    #[arg(short, long, group = "range_end")]
     duration: Option<String>,
 
+    /// End time for the image range in any of the --start formats, instead of --duration. Has
+    /// to be after the start and not in the future
+    #[arg(long, group = "range_end")]
+    end: Option<String>,
+
+    /// IANA timezone (e.g. "America/Chicago") that --start and --end without an offset are
+    /// in. Defaults to the system timezone
+    #[arg(long, value_name = "IANA")]
+    timezone: Option<Tz>,
+
     /// Time stride for the images in minutes (default: 10)
     #[arg(short, long, default_value = "10")]
     stride: i64,
 
-    /// Root directory to save images (default: current working directory)
+    /// Satellite to fetch from, case-insensitive: "east" (GOES-16 for frames before the 2025-04-07
+    /// handover, GOES-19 from then on), "goes16" or "goes19" to pin one, or "west" (GOES-18)
+    #[arg(long, default_value = "east", value_parser = goesimages::Sat::from_str)]
+    sat: goesimages::Sat,
+
+    /// Root directory to save images (default: current working directory).
+    /// Resolved to an absolute path at startup
     #[arg(short, long, default_value = ".")]
     root: String,
 
-    /// Maximum number of parallel threads (default: 8)
+    /// Maximum number of parallel threads (default: 8), or "auto" to derive it from the
+    /// available CPU parallelism (capped at 32)
     #[arg(short, long, default_value = "8")]
-    max_threads: usize,
+    max_threads: MaxThreads,
+
+    /// IANA timezone (e.g. "America/Chicago") used for output filenames, the subdirectory name
+    /// and log timestamps, which get an offset suffix ("Z" for UTC, otherwise e.g. "-0600").
+    /// This only affects presentation: frames are always selected and fetched in UTC.
+    /// When omitted, names use plain UTC timestamps without a suffix
+    #[arg(long, value_name = "IANA")]
+    timezone_output: Option<Tz>,
+
+    /// File name for each frame, built from {sat}, {product}, {sector}, {res} (WIDTHxHEIGHT),
+    /// {time} and {time:FORMAT} with a strftime FORMAT. Times are in --timezone-output. Include
+    /// the satellite, product and sector to keep frames of different runs apart in one folder,
+    /// e.g. "{sat}_{product}_{sector}_{time}.jpg"
+    #[arg(long, value_name = "TEMPLATE", default_value = plan::DEFAULT_NAME_TEMPLATE, value_parser = NameTemplate::from_str)]
+    name_template: NameTemplate,
+
+    /// Hash each frame as it is written, read it back from disk to confirm the hash matches,
+    /// and record it in a SHA256SUMS file in the subdirectory. Slower, but catches write
+    /// corruption during the run; a read-back mismatch counts as a failed frame
+    #[arg(long)]
+    verify_on_download: bool,
+
+    /// Fixed "current time" in ISO 8601 format used instead of the system clock when resolving
+    /// --ago and checking the range, for reproducible batch jobs and tests
+    #[arg(long, env = "GOES_NOW")]
+    now: Option<DateTime<Utc>>,
+
+    /// After downloading, group the frames by UTC hour, write a small animated GIF per hour and
+    /// an index.html in the subdirectory listing them chronologically. Requires the `imaging`
+    /// cargo feature
+    #[arg(long)]
+    hourly_previews: bool,
+
+    /// Skip the pre-flight check that the CDN is reachable over TLS and the local clock agrees
+    /// with the CDN's before any downloads start
+    #[arg(long)]
+    skip_preflight: bool,
+
+    /// Shell command to run once after the run finishes. Its inputs are environment variables,
+    /// so paths never need quoting into the command: GOESDOWN_DIR (output subdirectory),
+    /// GOESDOWN_MANIFEST (its manifest.json), GOESDOWN_TOTAL, GOESDOWN_SUCCEEDED,
+    /// GOESDOWN_NOT_FOUND (frames NOAA doesn't have) and GOESDOWN_FAILED (every other error),
+    /// e.g. 'rsync -a "$GOESDOWN_DIR" backup:' (%GOESDOWN_DIR% with cmd)
+    #[arg(long, value_name = "CMD", value_parser = parse_on_complete)]
+    on_complete: Option<String>,
+
+    /// Exit with the --on-complete command's exit code when it fails
+    #[arg(long, requires = "on_complete")]
+    propagate_on_complete: bool,
+
+    /// Skip timestamps where the sun is below the horizon. Judged at --lat/--lon when given,
+    /// otherwise at the satellite's sub-point on the equator, using a low precision solar
+    /// position (accurate to well under a degree) without refraction
+    #[arg(long)]
+    daylight_only: bool,
+
+    /// Only fetch frames from this long (like "1h", at most a day) before local sunrise at
+    /// --lat/--lon each day. Frames after sunset are kept up to --after-sunset. Days where the sun never sets
+    /// are kept whole and days where it never rises are skipped
+    #[arg(long, value_name = "DURATION", value_parser = parse_sun_offset, requires = "lat", conflicts_with = "daylight_only")]
+    before_sunrise: Option<Duration>,
+
+    /// Only fetch frames up to this long (like "1h", at most a day) after local sunset at
+    /// --lat/--lon each day. Frames before sunrise are kept from --before-sunrise
+    #[arg(long, value_name = "DURATION", value_parser = parse_sun_offset, requires = "lat", conflicts_with = "daylight_only")]
+    after_sunset: Option<Duration>,
+
+    /// Latitude in degrees (north positive) of the location used by --daylight-only,
+    /// --before-sunrise and --after-sunset
+    #[arg(long, requires = "lon", allow_negative_numbers = true, value_parser = parse_latitude)]
+    lat: Option<f64>,
+
+    /// Longitude in degrees (east positive) of the location used by --daylight-only,
+    /// --before-sunrise and --after-sunset
+    #[arg(long, requires = "lat", allow_negative_numbers = true, value_parser = parse_longitude)]
+    lon: Option<f64>,
+
+    /// Print how many responses used each HTTP version at the end of the run. reqwest doesn't
+    /// expose connection reuse, so only the negotiated version is reported
+    #[arg(long)]
+    http_version_report: bool,
+
+    /// JSON job file providing any of "start", "ago", "duration", "end", "stride", "root",
+    /// "max_threads", "sat", "product", "sector" and "resolution" (same meaning and values as
+    /// the flags). Flags given on the command line override it
+    #[arg(long, value_name = "FILE")]
+    job: Option<String>,
+
+    /// After downloading, compare each image by hash against the same-named file in this
+    /// directory (e.g. an earlier run over the same range) and summarize added, removed,
+    /// changed and identical frames
+    #[arg(long, value_name = "DIR")]
+    compare_against: Option<String>,
+
+    /// Also write the --compare-against result as JSON to this path
+    #[arg(long, value_name = "FILE", requires = "compare_against")]
+    compare_report: Option<String>,
+
+    /// Print only the number of frames the range would fetch and exit, without touching the
+    /// network or the filesystem
+    #[arg(long, conflicts_with = "noaa_animation")]
+    dry_run_count: bool,
+
+    /// Check the range and output directory, then print every frame URL that would be fetched
+    /// and the total, without downloading or creating anything
+    #[arg(long, conflicts_with_all = ["noaa_animation", "dry_run_count"])]
+    dry_run: bool,
+
+    /// Fail a frame if the file name NOAA reveals, through a redirect or a Content-Disposition
+    /// header, isn't exactly the one requested (e.g. a cache serving a neighbouring frame)
+    #[arg(long)]
+    strict_filename_match: bool,
+
+    /// Record every downloaded frame (timestamp, satellite, product, sector, resolution, path,
+    /// size, SHA-256 and ETag) in this SQLite database, creating it if needed and updating
+    /// frames that were indexed before
+    #[arg(long, value_name = "PATH")]
+    index_db: Option<String>,
+
+    /// Leave out timestamps within this duration (like "15m" or "90s") of now since NOAA may
+    /// not have published them yet. Without a value, one publication cadence of --sector is used
+    #[arg(long, value_name = "DURATION", num_args = 0..=1)]
+    skip_future_within: Option<Option<String>>,
+
+    /// Unix domain socket to send a JSON line to for every frame as soon as it completes.
+    /// Best effort: events are dropped while nothing is listening or the consumer falls behind
+    #[arg(long, value_name = "PATH")]
+    event_socket: Option<String>,
+
+    /// Seconds an idle keep-alive connection to the CDN stays pooled before being closed.
+    /// Kept short by default so quiet periods don't leave connections the CDN already dropped
+    #[arg(long, value_name = "SECONDS", default_value = "30")]
+    pool_idle_timeout: u64,
+
+    /// Root URL of the CDN. Point it at a mirror, or a local server for testing, that serves the
+    /// same paths
+    #[arg(long, value_name = "URL", default_value = goesimages::DEFAULT_BASE_URL, value_parser = goesimages::parse_base_url)]
+    base_url: url::Url,
+
+    /// Send every request through this HTTP(S) proxy, e.g. "http://proxy.example:3128". Without
+    /// it the HTTPS_PROXY, HTTP_PROXY, ALL_PROXY and NO_PROXY environment variables are honored
+    #[arg(long, value_name = "URL", value_parser = parse_proxy)]
+    proxy: Option<url::Url>,
+
+    /// Log more: -v adds the URL of every request and other debug detail, -vv everything.
+    /// Log lines go to stderr
+    #[arg(short, long, action = clap::ArgAction::Count, conflicts_with = "quiet")]
+    verbose: u8,
+
+    /// Only log warnings and errors, and don't draw the progress bar. Reports like --dry-run
+    /// output and the final summary are still printed
+    #[arg(short, long)]
+    quiet: bool,
+
+    /// Seconds to wait for a connection, or for more data on one, before giving up on the
+    /// request. Timed out frames are retried like other connection errors
+    #[arg(long, value_name = "SECONDS", default_value = "30", value_parser = clap::value_parser!(u64).range(1..))]
+    timeout: u64,
+
+    /// Also limit each whole request, body included, to --timeout plus this many seconds per
+    /// megapixel of the chosen resolution: --timeout + SECONDS × width × height / 1,000,000.
+    /// With 0.5, a 1808x1808 frame gets 31.6s and a 10848x10848 one 88.8s
+    #[arg(long, value_name = "SECONDS", value_parser = parse_seconds_per_megapixel)]
+    timeout_per_megapixel: Option<f64>,
+
+    /// How many more times to try a frame after a connection error or a 5xx response, backing
+    /// off exponentially from half a second with jitter. Other failures, like a 404 for a frame
+    /// that doesn't exist yet, are never retried
+    #[arg(long, value_name = "N", default_value = "3")]
+    retries: u32,
+
+    /// Give up on a frame once this many seconds have passed since its first attempt, backoff
+    /// included, even if it has retries left. Keeps one bad frame from holding up a run while
+    /// the CDN is struggling
+    #[arg(long, value_name = "SECONDS", value_parser = clap::value_parser!(u64).range(1..=86_400))]
+    retry_total_timeout: Option<u64>,
+
+    /// Start at most this many requests per second across all parallel downloads, to go easy on
+    /// NOAA's public CDN. Independent of --max-threads, which caps how many are in flight
+    #[arg(long, value_name = "PER_SECOND", value_parser = parse_rate, conflicts_with = "delay")]
+    rate_limit: Option<f64>,
+
+    /// Wait at least this many milliseconds between the starts of two requests, at most a day
+    #[arg(long, value_name = "MS", value_parser = clap::value_parser!(u64).range(..=86_400_000))]
+    delay: Option<u64>,
+
+    /// Read each downloaded JPEG's header and fail the frame unless its dimensions match the
+    /// requested resolution
+    #[arg(long)]
+    verify_dimensions: bool,
+
+    /// Imagery to download: geocolor, air-mass, sandwich, day-cloud-phase, fire-temperature,
+    /// dust or a single band from band01 to band16. NOAA's own names (like "AirMass" or "02")
+    /// work too
+    #[arg(long, default_value = "geocolor", value_parser = goesimages::Product::from_str)]
+    product: goesimages::Product,
+
+    /// Area to download: fd (full disk, every 10 minutes), conus (every 5 minutes) or the
+    /// movable mesoscale sectors meso1 and meso2 (every minute). --stride has to be a
+    /// multiple of the sector's cadence
+    #[arg(long, default_value = "fd", value_parser = goesimages::Sector::from_str)]
+    sector: goesimages::Sector,
+
+    /// Frame size to download: thumbnail (339x339), low (678x678), medium (1808x1808), high
+    /// (5424x5424), full (10848x10848) or, for band02 only, max (21696x21696). The pixel sizes
+    /// are accepted too. best and worst pick the largest or smallest size the sector and
+    /// product are published at
+    #[arg(long, default_value = "medium", value_parser = goesimages::ResolutionChoice::from_str)]
+    resolution: goesimages::ResolutionChoice,
+
+    /// Download every frame at the sector's smallest published size (339x339 for fd, 416x250
+    /// for conus, 250x250 for meso1/meso2) instead of --resolution, for a quick, low bandwidth
+    /// overview of a range. Pairs well with --contact-sheet and --hourly-previews. Same as
+    /// --resolution thumbnail
+    #[arg(long, conflicts_with = "resolution")]
+    thumbnails: bool,
+
+    /// List the gaps before exiting if any requested timestamp ended up missing or failed, for
+    /// datasets that must be complete. The exit code is a failure either way
+    #[arg(long)]
+    fail_on_any_missing: bool,
+
+    /// Reuse the subdirectory if an earlier run over the same range already created it, and
+    /// skip every frame already saved there. Empty files, like one left by a crash mid-write,
+    /// are downloaded again
+    #[arg(long)]
+    resume: bool,
+
+    /// When a frame 404s, save the closest earlier publication of the sector instead, going
+    /// back at most 3 publications. It is saved under its own time, and the manifest and the
+    /// summary note the substitution. Useful with --ago, where the newest frame may not be up yet
+    #[arg(long)]
+    nearest: bool,
+
+    /// Reuse the subdirectory if an earlier run over the same range already created it, and
+    /// download every frame again over the files saved there, e.g. to pick up frames the CDN
+    /// published late. Refuses a directory holding files this tool doesn't write
+    #[arg(long)]
+    overwrite: bool,
+
+    /// With --overwrite, reuse the subdirectory even if it holds unexpected files
+    #[arg(long, requires = "overwrite")]
+    force: bool,
+
+    /// Keep a latest.jpg in the subdirectory pointing at the newest downloaded frame, updated
+    /// atomically after each frame (a symlink, or a copy on Windows)
+    #[arg(long)]
+    write_latest_symlink: bool,
+
+    /// Abort before downloading if pre-flight measures local-vs-CDN clock skew above this
+    /// duration (like "90s" or "2m"). Without it, large skew is only a warning
+    #[arg(long, value_name = "DURATION", value_parser = parse_span, conflicts_with = "skip_preflight")]
+    abort_if_clock_skew_exceeds: Option<Duration>,
+
+    /// After downloading, also write every frame in chronological order as the pages of a
+    /// single multi-page TIFF at this path, each page tagged with its timestamp. Requires the
+    /// `imaging` cargo feature
+    #[arg(long, value_name = "FILE")]
+    tiff_stack: Option<String>,
+
+    /// After downloading, tile every saved frame in chronological order into one JPEG at this
+    /// path, each frame in a square cell the size of the sector's thumbnail. With --thumbnails that's a whole day on one page for a few MB
+    /// of downloads. Requires the `imaging` cargo feature
+    #[arg(long, value_name = "FILE")]
+    contact_sheet: Option<String>,
+
+    /// After downloading, stitch every saved frame in chronological order into an animation at
+    /// this path, leaving out frames that failed. A .gif is encoded in-process (scaled to fit
+    /// 1024 pixels, requires the `imaging` cargo feature); anything else, like .mp4 or .webm, is
+    /// piped to `ffmpeg`, which has to be on the PATH and picks the codec from the extension
+    #[arg(long, value_name = "FILE")]
+    timelapse: Option<String>,
+
+    /// Frames per second of the --timelapse output
+    #[arg(long, default_value = "10", value_parser = clap::value_parser!(u32).range(1..), requires = "timelapse")]
+    fps: u32,
+}
+
+/// Upper bound for `--max-threads auto` so many-core machines don't flood the CDN
+const MAX_AUTO_THREADS: usize = 32;
+
+/// Download concurrency, either given explicitly or derived from the machine
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum MaxThreads {
+    Auto,
+    Fixed(usize),
+}
+
+impl MaxThreads {
+    /// Downloads are network bound, so auto allows a couple of requests in flight per core
+    fn resolve(self) -> usize {
+        match self {
+            MaxThreads::Auto => std::thread::available_parallelism()
+                .map_or(8, |n| n.get() * 2)
+                .clamp(1, MAX_AUTO_THREADS),
+            MaxThreads::Fixed(n) => n,
+        }
+    }
+}
+
+impl FromStr for MaxThreads {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.eq_ignore_ascii_case("auto") {
+            return Ok(MaxThreads::Auto);
+        }
+        match s.parse() {
+            Ok(0) => Err("must be at least 1".to_string()),
+            Ok(n) => Ok(MaxThreads::Fixed(n)),
+            Err(_) => Err(format!("expected a number or \"auto\", got '{}'", s)),
+        }
+    }
+}
+
+/// Job files can give either `4` or `"auto"`, checked the same way as --max-threads
+impl<'de> Deserialize<'de> for MaxThreads {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Raw {
+            Number(u64),
+            Text(String),
+        }
+        let text = match Raw::deserialize(deserializer)? {
+            Raw::Number(n) => n.to_string(),
+            Raw::Text(text) => text,
+        };
+        text.parse()
+            .map_err(|e| serde::de::Error::custom(format!("invalid max_threads: {}", e)))
+    }
+}
+
+/// The subset of the CLI that can be specified in a `--job` file
+#[derive(Deserialize, Debug, Default)]
+#[serde(deny_unknown_fields)]
+struct JobFile {
+    start: Option<String>,
+    ago: Option<String>,
+    duration: Option<String>,
+    end: Option<String>,
+    stride: Option<i64>,
+    root: Option<String>,
+    max_threads: Option<MaxThreads>,
+    #[serde(default, deserialize_with = "parse_job_field")]
+    sat: Option<goesimages::Sat>,
+    #[serde(default, deserialize_with = "parse_job_field")]
+    product: Option<goesimages::Product>,
+    #[serde(default, deserialize_with = "parse_job_field")]
+    sector: Option<goesimages::Sector>,
+    #[serde(default, deserialize_with = "parse_job_field")]
+    resolution: Option<goesimages::ResolutionChoice>,
+}
+
+/// Reads a job file string with the same parser as the matching flag, so it accepts the same
+/// names and aliases
+fn parse_job_field<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+    T: FromStr<Err = String>,
+{
+    String::deserialize(deserializer)?
+        .parse()
+        .map(Some)
+        .map_err(serde::de::Error::custom)
+}
+
+impl JobFile {
+    fn load(path: &str) -> Result<JobFile, String> {
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read job file '{}': {}", path, e))?;
+        let job: JobFile = serde_json::from_str(&contents)
+            .map_err(|e| format!("Invalid job file '{}': {}", path, e))?;
+        job.validate()
+            .map_err(|e| format!("Invalid job file '{}': {}", path, e))?;
+        Ok(job)
+    }
+
+    /// Catches combinations the flags' argument groups would reject
+    fn validate(&self) -> Result<(), String> {
+        if self.duration.is_some() && self.end.is_some() {
+            return Err("give either \"duration\" or \"end\", not both".to_string());
+        }
+        Ok(())
+    }
+
+    /// Fills in every field the user didn't explicitly pass on the command line
+    fn apply(self, cli: &mut Cli, matches: &ArgMatches) {
+        let explicit = |id: &str| {
+            matches!(
+                matches.value_source(id),
+                Some(ValueSource::CommandLine | ValueSource::EnvVariable)
+            )
+        };
+
+        // The start of the range is one choice, so any of these on the command line replaces
+        // whatever the job file picked
+        if !["start", "ago", "noaa_animation"]
+            .iter()
+            .any(|id| explicit(id))
+        {
+            cli.start = self.start;
+            cli.ago = self.ago;
+        }
+        // Likewise for the end of the range, so --duration replaces the job file's end and --end
+        // its duration
+        if !explicit("duration")
+            && !explicit("end")
+            && (self.duration.is_some() || self.end.is_some())
+        {
+            cli.duration = self.duration;
+            cli.end = self.end;
+        }
+        if let (false, Some(stride)) = (explicit("stride"), self.stride) {
+            cli.stride = stride;
+        }
+        if let (false, Some(root)) = (explicit("root"), self.root) {
+            cli.root = root;
+        }
+        if let (false, Some(max_threads)) = (explicit("max_threads"), self.max_threads) {
+            cli.max_threads = max_threads;
+        }
+        if let (false, Some(sat)) = (explicit("sat"), self.sat) {
+            cli.sat = sat;
+        }
+        if let (false, Some(product)) = (explicit("product"), self.product) {
+            cli.product = product;
+        }
+        if let (false, Some(sector)) = (explicit("sector"), self.sector) {
+            cli.sector = sector;
+        }
+        // --thumbnails is a resolution too
+        if let (false, false, Some(resolution)) = (
+            explicit("resolution"),
+            explicit("thumbnails"),
+            self.resolution,
+        ) {
+            cli.resolution = resolution;
+        }
+    }
+}
+
+/// The validated set of timestamps to fetch
+struct TimeRange {
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+    stride: i64,
+    /// Frames after this are considered not yet published (from --skip-future-within)
+    publish_cutoff: Option<DateTime<Utc>>,
+}
+
+/// The timestamps that will actually be requested after filtering a [`TimeRange`]
+struct PlannedTimes {
+    times: Vec<DateTime<Utc>>,
+    /// Dropped by --daylight-only
+    night_frames: usize,
+    /// Dropped by --before-sunrise/--after-sunset
+    outside_sun_window: usize,
+    /// Local solar dates in the range where the sun never rises or never sets
+    polar_dates: Vec<(NaiveDate, solar::Daylight)>,
+    /// Dropped by --skip-future-within
+    unpublished_frames: usize,
+}
+
+impl TimeRange {
+    fn iter(&self) -> impl Iterator<Item = DateTime<Utc>> {
+        download::frame_times(self.start, self.end, self.stride)
+    }
 }
 
 impl Cli {
-    fn validate_and_parse(
-        &self,
-    ) -> Result<
-        (
-            impl Iterator<Item = DateTime<Utc>>,
-            DateTime<Utc>,
-            DateTime<Utc>,
-            i64,
-        ),
-        String,
-    > {
-        let current_time = Utc::now();
+    fn validate_and_parse(&self) -> Result<TimeRange, String> {
+        let current_time = self.now.unwrap_or_else(Utc::now);
 
         // Parse start time or calculate it using "ago"
-        let start_time = match (&self.start, &self.ago) {
-            (Some(start), None) => DateTime::<Utc>::from_str(start)
-                .map_err(|e| format!("Invalid start time: {}", e))?,
-            (None, Some(ago)) => {
-                let duration = parse_duration(ago)?;
-                let time = current_time - duration;
-                round_to_previous_10_minutes(time)
+        let start_time = match (&self.start, &self.ago, &self.since_manifest) {
+            (Some(start), None, None) => {
+                let requested = parse_start_time(start, self.timezone.as_ref())
+                    .map_err(|e| format!("Invalid start time: {}", e))?;
+                // Frames only exist on the sector's publication grid, so an offset start would
+                // have every frame 404
+                let cadence = self.sector.cadence_minutes();
+                let snapped = round_to_previous_cadence(requested, cadence);
+                if snapped != requested {
+                    warn!(
+                        "start time {} isn't on the {}-minute grid {} frames are published on, using {}",
+                        requested,
+                        cadence,
+                        self.sector.url_frag(),
+                        snapped
+                    );
+                }
+                snapped
+            }
+            (None, Some(ago), None) => {
+                let duration = parse_duration(ago, self.sector.min_stride_minutes())
+                    .map_err(|e| e.to_string())?;
+                let time = current_time
+                    .checked_sub_signed(duration)
+                    .ok_or_else(|| format!("--ago {} is too far back", ago))?;
+                round_to_previous_cadence(time, self.sector.cadence_minutes())
+            }
+            (None, None, Some(path)) => {
+                let newest = Manifest::read(Path::new(path))?
+                    .newest_saved()
+                    .ok_or_else(|| format!("{} doesn't record any saved frames", path))?;
+                let next = round_to_previous_cadence(newest, self.sector.cadence_minutes())
+                    + Duration::minutes(self.stride.clamp(1, download::MAX_STRIDE_MINUTES));
+                // Nothing newer has been published until the latest frame is at least `next`
+                if next > round_to_previous_cadence(current_time, self.sector.cadence_minutes()) {
+                    return Err(format!(
+                        "Nothing to download yet: the newest frame in {} is from {}",
+                        path, newest
+                    ));
+                }
+                next
+            }
+            _ => {
+                return Err(
+                    "You must specify one of --start, --ago or --since-manifest (as a flag or in \
+                 --job)"
+                        .to_string(),
+                )
             }
-            _ => return Err("You must specify either --start or --ago, but not both".to_string()),
         };
 
         // Validate time range
@@ -72,9 +642,30 @@ impl Cli {
         }
 
         // Parse or calculate duration
-        let end_time = match &self.duration {
-            Some(dur) => round_to_previous_10_minutes(start_time + parse_duration(dur)?),
-            None => round_to_previous_10_minutes(current_time),
+        let end_time = match (&self.duration, &self.end) {
+            (Some(dur), None) => {
+                let duration = parse_duration(dur, self.sector.min_stride_minutes())
+                    .map_err(|e| e.to_string())?;
+                let end = start_time
+                    .checked_add_signed(duration)
+                    .ok_or_else(|| format!("--duration {} is too long", dur))?;
+                round_to_previous_cadence(end, self.sector.cadence_minutes())
+            }
+            (None, Some(end)) => {
+                let end = parse_start_time(end, self.timezone.as_ref())
+                    .map_err(|e| format!("Invalid end time: {}", e))?;
+                if end <= start_time {
+                    return Err(format!(
+                        "End time ({}) must be after the start time ({})",
+                        end, start_time
+                    ));
+                }
+                truncate_to_minute(end)
+            }
+            (None, None) => round_to_previous_cadence(current_time, self.sector.cadence_minutes()),
+            (Some(_), Some(_)) => {
+                return Err("Specify either --duration or --end, but not both".to_string())
+            }
         };
 
         if end_time > current_time {
@@ -84,39 +675,300 @@ impl Cli {
             ));
         }
 
-        if self.stride % 10 != 0 {
-            return Err(format!("Stride ({}) must be a multiple of 10", self.stride));
+        goesimages::published_dimensions(&self.sector, &self.product, &self.resolution())?;
+
+        let min_stride = self.sector.min_stride_minutes();
+        if self.stride <= 0 || self.stride % min_stride != 0 {
+            return Err(format!(
+                "Stride ({}) must be a multiple of {}, how often the sector is published",
+                self.stride, min_stride
+            ));
+        }
+        if self.stride > download::MAX_STRIDE_MINUTES {
+            return Err(format!(
+                "Stride ({}) is longer than the {} minutes of frames NOAA keeps",
+                self.stride,
+                download::MAX_STRIDE_MINUTES
+            ));
+        }
+        // A range that isn't a whole number of strides just ends on the last frame before the
+        // end time, so it isn't an error
+
+        // A cutoff doesn't have to sit on the frame grid, so any span works
+        let publish_cutoff = match &self.skip_future_within {
+            Some(Some(window)) => {
+                let window = parse_span(window).map_err(|e| e.to_string())?;
+                Some(
+                    current_time
+                        .checked_sub_signed(window)
+                        .ok_or_else(|| "--skip-future-within is too long".to_string())?,
+                )
+            }
+            Some(None) => Some(current_time - Duration::minutes(self.sector.cadence_minutes())),
+            None => None,
+        };
+
+        Ok(TimeRange {
+            start: start_time,
+            end: end_time,
+            stride: self.stride,
+            publish_cutoff,
+        })
+    }
+
+    /// Every timestamp in `range` that will actually be requested
+    fn planned_times(&self, range: &TimeRange) -> PlannedTimes {
+        let mut times: Vec<_> = range.iter().collect();
+
+        let planned = times.len();
+        if let Some(cutoff) = range.publish_cutoff {
+            times.retain(|time| *time <= cutoff);
+        }
+        let unpublished_frames = planned - times.len();
+
+        let planned = times.len();
+        if self.daylight_only {
+            times.retain(|time| self.is_daylight(time));
+        }
+        let night_frames = planned - times.len();
+
+        let planned = times.len();
+        let mut polar_dates = Vec::new();
+        if let (true, Some(lat), Some(lon)) = (self.uses_sun_window(), self.lat, self.lon) {
+            let before = self.before_sunrise.unwrap_or_else(Duration::zero);
+            let after = self.after_sunset.unwrap_or_else(Duration::zero);
+            let mut days = BTreeMap::new();
+            let mut daylight = |date: NaiveDate| {
+                *days
+                    .entry(date)
+                    .or_insert_with(|| solar::daylight(date, lat, lon))
+            };
+            times.retain(|time| {
+                let date = solar::solar_date(time, lon);
+                // Long offsets can reach into the neighbouring days' windows
+                [date.pred_opt(), Some(date), date.succ_opt()]
+                    .into_iter()
+                    .flatten()
+                    .any(|date| match daylight(date) {
+                        solar::Daylight::Between { sunrise, sunset } => {
+                            sunrise - before <= *time && *time <= sunset + after
+                        }
+                        solar::Daylight::PolarDay => solar::solar_date(time, lon) == date,
+                        solar::Daylight::PolarNight => false,
+                    })
+            });
+            let range_dates: BTreeSet<_> = range
+                .iter()
+                .map(|time| solar::solar_date(&time, lon))
+                .collect();
+            polar_dates = days
+                .into_iter()
+                .filter(|(date, day)| {
+                    range_dates.contains(date) && !matches!(day, solar::Daylight::Between { .. })
+                })
+                .collect();
+        }
+        let outside_sun_window = planned - times.len();
+
+        PlannedTimes {
+            times,
+            night_frames,
+            outside_sun_window,
+            polar_dates,
+            unpublished_frames,
+        }
+    }
+
+    fn uses_sun_window(&self) -> bool {
+        self.before_sunrise.is_some() || self.after_sunset.is_some()
+    }
+
+    /// Whether the sun is up at `time` for the --daylight-only location
+    fn is_daylight(&self, time: &DateTime<Utc>) -> bool {
+        let (lat, lon) = match (self.lat, self.lon) {
+            (Some(lat), Some(lon)) => (lat, lon),
+            _ => (0.0, self.sat.sub_longitude()),
+        };
+        solar::solar_elevation(time, lat, lon) > 0.0
+    }
+
+    /// Rejects flags that need a cargo feature this binary was built without, before any work
+    /// starts
+    fn validate_features(&self) -> Result<(), String> {
+        let imaging_flags = [
+            ("--hourly-previews", self.hourly_previews),
+            ("--tiff-stack", self.tiff_stack.is_some()),
+            ("--contact-sheet", self.contact_sheet.is_some()),
+            (
+                "--timelapse with a .gif output",
+                self.timelapse.as_deref().is_some_and(is_gif),
+            ),
+        ];
+        if !cfg!(feature = "imaging") {
+            if let Some((flag, _)) = imaging_flags.iter().find(|(_, used)| *used) {
+                return Err(format!(
+                    "{} needs image processing support, rebuild with `cargo build --features imaging`",
+                    flag
+                ));
+            }
         }
+        Ok(())
+    }
 
-        // if (end_time - start_time).num_minutes() % self.stride != 0 {
-        //     return Err(format!(
-        //         "Duration ({}) must be a multiple of the stride ({})",
-        //         self.duration,
-        //         self.stride
-        //     ));
-        // }
+    /// The HTTP client for the run, configured by --proxy, --timeout and --pool-idle-timeout.
+    /// A malformed proxy in the environment is an error here rather than silently ignored
+    fn build_client(&self) -> Result<Client, String> {
+        let timeout = std::time::Duration::from_secs(self.timeout);
+        let mut builder = Client::builder()
+            .pool_idle_timeout(std::time::Duration::from_secs(self.pool_idle_timeout))
+            .connect_timeout(timeout)
+            .read_timeout(timeout);
+        match &self.proxy {
+            Some(proxy) => {
+                let proxy = reqwest::Proxy::all(proxy.as_str())
+                    .map_err(|e| format!("Invalid proxy '{}': {}", proxy, e))?;
+                builder = builder.proxy(proxy);
+            }
+            None => check_proxy_env(|name| std::env::var(name).ok())?,
+        }
+        builder
+            .build()
+            .map_err(|e| format!("Failed to build HTTP client: {}", e))
+    }
 
-        let time_delta = TimeDelta::minutes(self.stride);
-        Ok((
-            std::iter::successors(Some(start_time), move |&prev| -> Option<DateTime<Utc>> {
-                let next = prev + time_delta;
-                if next <= end_time {
-                    Some(next)
-                } else {
-                    None
+    /// Resolves `--root` to an absolute path so the output location doesn't depend on whatever
+    /// working directory the tool happened to be launched from
+    fn resolve_root(&mut self) -> Result<(), String> {
+        let canonical = Path::new(&self.root)
+            .canonicalize()
+            .map_err(|e| match e.kind() {
+                std::io::ErrorKind::NotFound => {
+                    format!("Specified root directory '{}' does not exist", self.root)
                 }
-            }),
-            start_time,
-            end_time,
-            self.stride,
+                _ => format!("Failed to resolve root directory '{}': {}", self.root, e),
+            })?;
+        if !canonical.is_dir() {
+            return Err(format!(
+                "Specified root '{}' is not a directory",
+                canonical.display()
+            ));
+        }
+        self.root = canonical.to_string_lossy().to_string();
+        Ok(())
+    }
+
+    /// Names the run's subdirectory after everything that decides which frames it holds, so
+    /// --resume never mistakes another product or size over the same range for this one
+    fn subdirectory_name(
+        &self,
+        start_time: DateTime<Utc>,
+        end_time: DateTime<Utc>,
+    ) -> Result<String, String> {
+        let (width, height) =
+            goesimages::published_dimensions(&self.sector, &self.product, &self.resolution())?;
+        Ok(format!(
+            "images_{}_{}_{}_{}x{}_{}_to_{}_stride_{}m",
+            self.sat.operational_at(&start_time).url_frag(),
+            self.sector.url_frag(),
+            self.product.url_frag(),
+            width,
+            height,
+            format_output_time(&start_time, self.timezone_output.as_ref()),
+            format_output_time(&end_time, self.timezone_output.as_ref()),
+            self.stride
         ))
     }
 
+    /// Paces requests by --rate-limit or --delay, if either is given
+    fn rate_limiter(&self) -> Option<RateLimiter> {
+        match (self.rate_limit, self.delay) {
+            (Some(per_second), _) => Some(RateLimiter::per_second(per_second)),
+            (None, Some(ms)) => Some(RateLimiter::new(std::time::Duration::from_millis(ms))),
+            (None, None) => None,
+        }
+    }
+
+    /// --resolution with best and worst resolved for the sector and product, or the thumbnail
+    /// size with --thumbnails
+    fn resolution(&self) -> goesimages::Resolution {
+        if self.thumbnails {
+            goesimages::Resolution::Thumbnail
+        } else {
+            self.resolution.resolve(&self.sector, &self.product)
+        }
+    }
+
+    /// The --timeout-per-megapixel limit for the run's frames, which all share one resolution
+    fn frame_timeout(&self) -> Option<std::time::Duration> {
+        let per_megapixel = self.timeout_per_megapixel?;
+        let dimensions =
+            goesimages::published_dimensions(&self.sector, &self.product, &self.resolution())
+                .ok()?;
+        let scaling = TimeoutScaling {
+            base: std::time::Duration::from_secs(self.timeout),
+            per_megapixel: std::time::Duration::from_secs_f64(per_megapixel),
+        };
+        Some(scaling.for_dimensions(dimensions))
+    }
+
+    fn fetch_config(&self, subdirectory: String, checksums: Option<Mutex<File>>) -> FetchConfig {
+        FetchConfig {
+            options: self.plan_options(subdirectory),
+            checksums,
+            http_versions: Mutex::new(BTreeMap::new()),
+            strict_filename_match: self.strict_filename_match,
+            hash_frames: self.index_db.is_some(),
+            verify_dimensions: self.verify_dimensions,
+            retries: self.retries,
+            retry_delay: RETRY_BASE_DELAY,
+            retry_total_timeout: self.retry_total_timeout.map(std::time::Duration::from_secs),
+            resume: self.resume,
+            rate_limit: self.rate_limiter(),
+            nearest_steps: if self.nearest { NEAREST_MAX_STEPS } else { 0 },
+            frame_timeout: self.frame_timeout(),
+        }
+    }
+
+    fn plan_options(&self, subdirectory: String) -> PlanOptions {
+        PlanOptions {
+            sat: self.sat,
+            sector: self.sector,
+            product: self.product,
+            resolution: self.resolution(),
+            subdirectory,
+            timezone_output: self.timezone_output,
+            name_template: self.name_template.clone(),
+            base_url: self.base_url.clone(),
+        }
+    }
+
+    /// Creates the run's subdirectory, or with --resume reuses an existing one. Returns its path
+    /// and whether it already existed
     fn validate_directory(
         &self,
         start_time: DateTime<Utc>,
         end_time: DateTime<Utc>,
-    ) -> Result<String, String> {
+    ) -> Result<(String, bool), String> {
+        let (subdirectory_path, exists) = self.subdirectory_path(start_time, end_time)?;
+        if !exists {
+            std::fs::create_dir(&subdirectory_path).map_err(|e| {
+                format!(
+                    "Failed to create subdirectory '{}': {}",
+                    subdirectory_path.display(),
+                    e
+                )
+            })?;
+        }
+        Ok((subdirectory_path.to_string_lossy().to_string(), exists))
+    }
+
+    /// Where the run's subdirectory goes, checked the same way [`Cli::validate_directory`] does
+    /// but without creating anything. Also returns whether --resume or --overwrite will reuse it
+    fn subdirectory_path(
+        &self,
+        start_time: DateTime<Utc>,
+        end_time: DateTime<Utc>,
+    ) -> Result<(PathBuf, bool), String> {
         let root_path = Path::new(&self.root);
         if !root_path.exists() {
             return Err(format!(
@@ -125,134 +977,1944 @@ impl Cli {
             ));
         }
 
-        let subdirectory_name = format!(
-            "images_{}_to_{}_stride_{}m",
-            start_time.format("%Y%m%dT%H%M%S"),
-            end_time.format("%Y%m%dT%H%M%S"),
-            self.stride
-        );
-        let subdirectory_path = root_path.join(&subdirectory_name);
+        let subdirectory_path = root_path.join(self.subdirectory_name(start_time, end_time)?);
 
+        if (self.resume || self.overwrite) && subdirectory_path.is_dir() {
+            if self.overwrite && !self.force {
+                let unexpected = unexpected_files(&subdirectory_path, self.name_template.suffix())?;
+                if !unexpected.is_empty() {
+                    return Err(format!(
+                        "Subdirectory '{}' holds files that aren't from a download ({}), pass --force to overwrite it anyway",
+                        subdirectory_path.display(),
+                        unexpected.join(", ")
+                    ));
+                }
+            }
+            return Ok((subdirectory_path, true));
+        }
         if subdirectory_path.exists() {
             return Err(format!(
                 "Subdirectory '{}' already exists",
                 subdirectory_path.display()
             ));
         }
+        Ok((subdirectory_path, false))
+    }
 
-        std::fs::create_dir(&subdirectory_path).map_err(|e| {
-            format!(
-                "Failed to create subdirectory '{}': {}",
-                subdirectory_path.display(),
-                e
-            )
-        })?;
-
-        Ok(subdirectory_path.to_string_lossy().to_string())
+    /// Everything a real run would fetch, validated like one but without touching the network
+    /// or the filesystem
+    fn dry_run_plan(&self) -> Result<DownloadPlan, String> {
+        let range = self.validate_and_parse()?;
+        let (subdirectory, _) = self.subdirectory_path(range.start, range.end)?;
+        plan::plan(
+            &self.plan_options(subdirectory.to_string_lossy().to_string()),
+            self.planned_times(&range).times,
+        )
     }
 }
 
-fn parse_duration(input: &str) -> Result<Duration, String> {
-    let mut total_minutes = 0;
-    let mut value = String::new();
+/// The formats [`parse_start_time`] accepts, for error messages
+const TIME_FORMATS: &str = "RFC 3339 with an offset (2024-11-30T12:00:00Z), a local date and \
+    time (2024-11-30T12:00:00, 2024-11-30 12:00) or a local date (2024-11-30)";
 
-    for c in input.chars() {
-        if c.is_digit(10) {
-            value.push(c);
-        } else {
-            let num: i64 = value.parse().map_err(|_| "Invalid duration value")?;
-            value.clear();
-            total_minutes += match c {
-                'm' => num,
-                'h' => num * 60,
-                'd' => num * 1440,
-                _ => return Err("Unsupported duration unit. Use m, h, or d".to_string()),
-            };
-        }
+/// Parses a --start or --end. RFC 3339 times carry their own offset; a date and time without one
+/// is read in `tz` (the system timezone when unset) and a bare date is midnight there. A local
+/// time repeated by a daylight saving change resolves to its first occurrence
+fn parse_start_time(input: &str, tz: Option<&Tz>) -> Result<DateTime<Utc>, String> {
+    if let Ok(time) = DateTime::<Utc>::from_str(input) {
+        return Ok(time);
     }
+    let naive = [
+        "%Y-%m-%dT%H:%M:%S%.f",
+        "%Y-%m-%d %H:%M:%S%.f",
+        "%Y-%m-%dT%H:%M",
+        "%Y-%m-%d %H:%M",
+    ]
+    .iter()
+    .find_map(|format| NaiveDateTime::parse_from_str(input, format).ok())
+    .or_else(|| {
+        NaiveDate::parse_from_str(input, "%Y-%m-%d")
+            .ok()
+            .and_then(|date| date.and_hms_opt(0, 0, 0))
+    })
+    .ok_or_else(|| format!("'{}' is not a time, expected {}", input, TIME_FORMATS))?;
+    let resolved = match tz {
+        Some(tz) => tz
+            .from_local_datetime(&naive)
+            .earliest()
+            .map(|time| time.with_timezone(&Utc)),
+        None => Local
+            .from_local_datetime(&naive)
+            .earliest()
+            .map(|time| time.with_timezone(&Utc)),
+    };
+    resolved.ok_or_else(|| {
+        format!(
+            "'{}' doesn't exist in {}, it falls in a daylight saving gap",
+            input,
+            tz.map_or("the system timezone".to_string(), |tz| tz.to_string())
+        )
+    })
+}
 
-    if total_minutes % 10 != 0 {
-        return Err("Duration must be a multiple of 10 minutes".to_string());
+/// Names of entries in `dir` that no run writes: anything besides frames (JPEGs, or whatever
+/// `frame_suffix` the name template ends in), previews, staging files and the checksum,
+/// manifest and preview index files
+fn unexpected_files(dir: &Path, frame_suffix: Option<&str>) -> Result<Vec<String>, String> {
+    let entries = std::fs::read_dir(dir)
+        .map_err(|e| format!("Failed to read subdirectory '{}': {}", dir.display(), e))?;
+    let mut unexpected = Vec::new();
+    for entry in entries {
+        let entry =
+            entry.map_err(|e| format!("Failed to read subdirectory '{}': {}", dir.display(), e))?;
+        let name = entry.file_name().to_string_lossy().to_string();
+        let is_file = entry
+            .file_type()
+            .is_ok_and(|t| t.is_file() || t.is_symlink());
+        let written_by_run = ["SHA256SUMS", MANIFEST_FILE, "index.html"].contains(&name.as_str())
+            || [".jpg", ".gif", ".tmp"]
+                .iter()
+                .chain(&frame_suffix)
+                .any(|ext| name.ends_with(ext));
+        if !is_file || !written_by_run {
+            unexpected.push(name);
+        }
     }
+    unexpected.sort();
+    Ok(unexpected)
+}
 
-    Ok(Duration::minutes(total_minutes))
+/// Drops seconds and sub-seconds, which the `%Y%j%H%M` frame naming can't represent
+fn truncate_to_minute(dt: DateTime<Utc>) -> DateTime<Utc> {
+    dt.with_second(0).unwrap().with_nanosecond(0).unwrap()
 }
 
-fn round_to_previous_10_minutes(dt: DateTime<Utc>) -> DateTime<Utc> {
-    let rounded_minutes = (dt.minute() / 10) * 10;
-    dt.with_minute(rounded_minutes)
-        .unwrap()
-        .with_second(0)
-        .unwrap()
+/// Rounds down to the previous multiple of `cadence` minutes within the hour
+fn round_to_previous_cadence(dt: DateTime<Utc>, cadence: i64) -> DateTime<Utc> {
+    let cadence = cadence as u32;
+    let rounded_minutes = (dt.minute() / cadence) * cadence;
+    truncate_to_minute(dt.with_minute(rounded_minutes).unwrap())
 }
 
 #[tokio::main]
 async fn main() {
-    let cli = Cli::parse();
-
-    match cli.validate_and_parse() {
-        Ok((time_iter, start_time, end_time, stride)) => {
-            match cli.validate_directory(start_time, end_time) {
-                Ok(subdirectory) => {
-                    println!("Created subdirectory: {}", subdirectory);
+    let matches = Cli::command().get_matches();
+    let mut cli = Cli::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+    // Drawn once the downloads start, but logging has to know about it from the beginning
+    let progress = ProgressBar::hidden();
+    init_logging(
+        log_level(cli.verbose, cli.quiet),
+        cli.timezone_output,
+        progress.clone(),
+    );
+    if let Some(path) = cli.job.clone() {
+        match JobFile::load(&path) {
+            Ok(job) => job.apply(&mut cli, &matches),
+            Err(e) => {
+                error!("Job Error: {}", e);
+                std::process::exit(1);
+            }
+        }
+    }
 
-                    println!(
-                        "Fetching images from {} to {} with a stride of {} minutes",
-                        start_time, end_time, stride
-                    );
+    if let Err(e) = cli.validate_features() {
+        error!("Error: {}", e);
+        std::process::exit(1);
+    }
 
-                    let client = Client::new();
-                    let semaphore = Arc::new(Semaphore::new(cli.max_threads));
-
-                    let tasks: Vec<_> = time_iter
-                        .map(|time| {
-                            let permit = semaphore.clone().acquire_owned();
-                            let client = client.clone();
-                            let subdirectory = subdirectory.clone();
-
-                            tokio::spawn(async move {
-                                let _permit = permit.await.unwrap();
-                                fetch_image(client, subdirectory, time).await
-                            })
-                        })
-                        .collect();
-
-                    for task in tasks {
-                        match task.await {
-                            Ok(Ok(path)) => println!("Saved image to {}", path),
-                            Ok(Err(e)) => eprintln!("Error fetching image: {}", e),
-                            Err(e) => eprintln!("Task panicked: {}", e),
-                        }
+    if cli.dry_run_count {
+        let range = cli.validate_and_parse().and_then(|range| {
+            let name = cli.subdirectory_name(range.start, range.end)?;
+            Ok((range, name))
+        });
+        match range {
+            Ok((range, name)) => {
+                let subdirectory = Path::new(&cli.root)
+                    .join(name)
+                    .to_string_lossy()
+                    .to_string();
+                match plan::plan(
+                    &cli.plan_options(subdirectory),
+                    cli.planned_times(&range).times,
+                ) {
+                    Ok(plan) => println!("{}", plan.entries.len()),
+                    Err(e) => {
+                        error!("Error: {}", e);
+                        std::process::exit(1);
                     }
                 }
-                Err(e) => eprintln!("Directory Error: {}", e),
+            }
+            Err(e) => {
+                error!("Error: {}", e);
+                std::process::exit(1);
             }
         }
-        Err(e) => eprintln!("Error: {}", e),
+        return;
     }
-}
 
-async fn fetch_image(
-    client: Client,
-    subdirectory: String,
-    time: DateTime<Utc>,
-) -> Result<String, String> {
-    let url = goesimages::construct_image_url(&goesimages::Sat::GoesEast, &time)
-        .map_err(|e| format!("Failed to construct url for time {time}: {e}"))?;
+    if let Err(e) = cli.resolve_root() {
+        error!("Directory Error: {}", e);
+        std::process::exit(1);
+    }
+    info!("Output root: {}", cli.root);
+    if !cli.thumbnails
+        && matches!(
+            cli.resolution,
+            goesimages::ResolutionChoice::Best | goesimages::ResolutionChoice::Worst
+        )
+    {
+        if let Ok((width, height)) =
+            goesimages::published_dimensions(&cli.sector, &cli.product, &cli.resolution())
+        {
+            info!("Resolution: {}x{}", width, height);
+        }
+    }
 
-    let response = client
-        .get(url.clone())
-        .send()
-        .await
-        .map_err(|e| format!("Failed to fetch {url}: {e}"))?;
+    if cli.dry_run {
+        match cli.dry_run_plan() {
+            Ok(plan) => {
+                for frame in &plan.entries {
+                    println!("{}", frame.url);
+                }
+                println!("{} frames would be downloaded", plan.entries.len());
+            }
+            Err(e) => {
+                error!("Error: {}", e);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
 
-    if !response.status().is_success() {
-        return Err(format!("Failed to fetch {url}: HTTP{}", response.status()));
+    let client = match cli.build_client() {
+        Ok(client) => client,
+        Err(e) => {
+            error!("Error: {}", e);
+            std::process::exit(1);
+        }
+    };
+    if cli.noaa_animation {
+        match fetch_noaa_animation(&client, &cli).await {
+            Ok(path) => info!("Saved NOAA animation to {}", path),
+            Err(e) => {
+                error!("Error fetching NOAA animation: {}", e);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
+    let range = match cli.validate_and_parse() {
+        Ok(range) => range,
+        Err(e) => {
+            error!("Error: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    if !cli.skip_preflight {
+        match preflight(&client, &cli.base_url, cli.abort_if_clock_skew_exceeds).await {
+            Ok(skew) => info!("Pre-flight OK (clock skew {}s)", skew.num_seconds()),
+            Err(e) => {
+                error!("Pre-flight Error: {}", e);
+                std::process::exit(1);
+            }
+        }
     }
 
-    let image_path = format!("{subdirectory}/{}.jpg", time.format("%Y%m%dT%H%M%S"));
-    let bytes = response.bytes().await.map_err(|e| format!("Failed to read response: {e}"))?;
-    tokio::fs::write(&image_path, bytes).await.map_err(|e| format!("Failed to save image: {e}"))?;
+    let subdirectory = match cli.validate_directory(range.start, range.end) {
+        Ok((subdirectory, false)) => {
+            info!("Created subdirectory: {}", subdirectory);
+            subdirectory
+        }
+        Ok((subdirectory, true)) => {
+            if cli.overwrite {
+                info!("Overwriting in existing subdirectory: {}", subdirectory);
+            } else {
+                info!("Resuming in existing subdirectory: {}", subdirectory);
+            }
+            subdirectory
+        }
+        Err(e) => {
+            error!("Directory Error: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    info!(
+        "Fetching images from {} to {} with a stride of {} minutes",
+        format_log_time(&range.start, cli.timezone_output.as_ref()),
+        format_log_time(&range.end, cli.timezone_output.as_ref()),
+        range.stride
+    );
 
-    Ok(image_path)
+    let checksums = if cli.verify_on_download {
+        let path = Path::new(&subdirectory).join("SHA256SUMS");
+        // Resumed runs keep the checksums of frames saved earlier
+        let file = File::options()
+            .create(true)
+            .append(cli.resume)
+            .write(true)
+            .truncate(!cli.resume)
+            .open(&path);
+        match file {
+            Ok(file) => Some(Mutex::new(file)),
+            Err(e) => {
+                error!("Failed to create {}: {}", path.display(), e);
+                std::process::exit(1);
+            }
+        }
+    } else {
+        None
+    };
+    let config = Arc::new(cli.fetch_config(subdirectory, checksums));
+
+    let index_writer = match &cli.index_db {
+        Some(path) => match FrameIndex::open(Path::new(path)) {
+            Ok(index) => {
+                let (tx, rx) = mpsc::unbounded_channel();
+                Some((
+                    tx,
+                    tokio::task::spawn_blocking(move || write_index(index, rx)),
+                ))
+            }
+            Err(e) => {
+                error!("Failed to open index database '{}': {}", path, e);
+                std::process::exit(1);
+            }
+        },
+        None => None,
+    };
+
+    let plan = cli.planned_times(&range);
+    if cli.skip_future_within.is_some() {
+        info!(
+            "Skipping {} trailing frames that may not be published yet",
+            plan.unpublished_frames
+        );
+    }
+    if cli.daylight_only {
+        info!(
+            "Skipping {} of {} frames that fall at night",
+            plan.night_frames,
+            plan.night_frames + plan.times.len()
+        );
+    }
+    if cli.uses_sun_window() {
+        for (date, day) in &plan.polar_dates {
+            match day {
+                solar::Daylight::PolarDay => {
+                    info!("The sun doesn't set on {}, keeping the whole day", date)
+                }
+                _ => info!("The sun doesn't rise on {}, skipping the whole day", date),
+            }
+        }
+        info!(
+            "Skipping {} of {} frames outside the sunrise/sunset window",
+            plan.outside_sun_window,
+            plan.outside_sun_window + plan.times.len()
+        );
+    }
+
+    let event_writer = match &cli.event_socket {
+        #[cfg(unix)]
+        Some(path) => Some(goesdown::events::spawn_socket_writer(PathBuf::from(path))),
+        #[cfg(not(unix))]
+        Some(_) => {
+            error!("--event-socket is only supported on Unix");
+            std::process::exit(1);
+        }
+        None => None,
+    };
+
+    if cli.max_threads == MaxThreads::Auto {
+        info!("Using {} parallel downloads", cli.max_threads.resolve());
+    }
+    let download_plan = match plan::plan(&config.options, plan.times) {
+        Ok(download_plan) => download_plan,
+        Err(e) => {
+            error!("Error: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    let total = download_plan.entries.len();
+    let interrupted = Arc::new(AtomicBool::new(false));
+    let downloads_done = Arc::new(AtomicBool::new(false));
+    tokio::spawn(watch_for_interrupt(
+        interrupted.clone(),
+        downloads_done.clone(),
+    ));
+    let dimensions = download_plan
+        .entries
+        .first()
+        .map(|frame| frame.dimensions)
+        .unwrap_or_default();
+    let records = Records {
+        manifest: Manifest::new(
+            &config.options,
+            dimensions,
+            range.start,
+            range.end,
+            range.stride,
+        ),
+        index: index_writer,
+        events: event_writer,
+    };
+    let Downloaded {
+        saved,
+        missing,
+        summary,
+    } = download_frames(
+        &cli,
+        &client,
+        &config,
+        download_plan.entries,
+        records,
+        &interrupted,
+        &progress,
+    )
+    .await;
+    downloads_done.store(true, Ordering::SeqCst);
+    if interrupted.load(Ordering::SeqCst) {
+        error!(
+            "Interrupted: {} of {} downloads completed",
+            summary.saved + summary.skipped + summary.failed,
+            total
+        );
+        summary.print(cli.resume, cli.nearest);
+        std::process::exit(130);
+    }
+    let succeeded = summary.saved + summary.skipped;
+
+    #[cfg(feature = "imaging")]
+    if let Some(output) = &cli.tiff_stack {
+        let frames = saved.clone();
+        let path = PathBuf::from(output);
+        match tokio::task::spawn_blocking(move || {
+            goesdown::stack::encode_tiff_stack(&frames, &path)
+        })
+        .await
+        {
+            Ok(Ok(())) => info!("Wrote {} frames to {}", saved.len(), output),
+            Ok(Err(e)) => error!("Error writing TIFF stack {}: {}", output, e),
+            Err(e) => error!("TIFF stack task panicked: {}", e),
+        }
+    }
+
+    #[cfg(feature = "imaging")]
+    if let Some(output) = &cli.contact_sheet {
+        let paths: Vec<String> = saved.iter().map(|(_, path)| path.clone()).collect();
+        let path = PathBuf::from(output);
+        // Full size thumbnails, and any larger frame scaled down to one
+        let cell = goesimages::Resolution::Thumbnail
+            .dimensions(&cli.sector, &cli.product)
+            .map_or(CONTACT_SHEET_CELL, |(width, height)| width.max(height));
+        match tokio::task::spawn_blocking(move || {
+            goesdown::contact_sheet::encode_contact_sheet(&paths, &path, cell)
+        })
+        .await
+        {
+            Ok(Ok(())) => info!("Wrote a {} frame contact sheet to {}", saved.len(), output),
+            Ok(Err(e)) => error!("Error writing contact sheet {}: {}", output, e),
+            Err(e) => error!("Contact sheet task panicked: {}", e),
+        }
+    }
+
+    if let Some(output) = &cli.timelapse {
+        let missing_note = if missing.is_empty() {
+            String::new()
+        } else {
+            format!(", leaving out {} missing", missing.len())
+        };
+        match write_timelapse(saved.clone(), output, cli.fps).await {
+            Ok(()) => info!(
+                "Wrote a {} frame timelapse to {}{}",
+                saved.len(),
+                output,
+                missing_note
+            ),
+            Err(e) => error!("Error writing timelapse {}: {}", output, e),
+        }
+    }
+
+    #[cfg(feature = "imaging")]
+    if cli.hourly_previews {
+        match write_hourly_previews(saved, &config).await {
+            Ok(index) => info!("Wrote hourly previews to {}", index),
+            Err(e) => error!("Error writing hourly previews: {}", e),
+        }
+    }
+
+    if let Some(reference) = &cli.compare_against {
+        let report = cli.compare_report.as_deref();
+        if let Err(e) = compare_against(&config.options.subdirectory, reference, report).await {
+            error!("Comparison Error: {}", e);
+        }
+    }
+
+    if cli.http_version_report {
+        println!("HTTP versions used:");
+        for (version, count) in config.http_versions.lock().unwrap().iter() {
+            println!("  {}: {} responses", version, count);
+        }
+    }
+
+    if let Some(command) = &cli.on_complete {
+        let manifest = Path::new(&config.options.subdirectory).join(MANIFEST_FILE);
+        let env = [
+            ("GOESDOWN_DIR", config.options.subdirectory.clone()),
+            ("GOESDOWN_MANIFEST", manifest.to_string_lossy().to_string()),
+            ("GOESDOWN_TOTAL", total.to_string()),
+            ("GOESDOWN_SUCCEEDED", succeeded.to_string()),
+            ("GOESDOWN_NOT_FOUND", summary.not_found.to_string()),
+            (
+                "GOESDOWN_FAILED",
+                (summary.failed - summary.not_found).to_string(),
+            ),
+        ];
+        match run_on_complete(command, &env).await {
+            Ok(status) if status.success() => {}
+            Ok(status) => {
+                error!("On-complete command '{}' failed: {}", command, status);
+                if cli.propagate_on_complete {
+                    std::process::exit(status.code().unwrap_or(1));
+                }
+            }
+            Err(e) => {
+                error!("Failed to run on-complete command '{}': {}", command, e);
+                if cli.propagate_on_complete {
+                    std::process::exit(1);
+                }
+            }
+        }
+    }
+
+    summary.print(cli.resume, cli.nearest);
+
+    if !missing.is_empty() {
+        if cli.fail_on_any_missing {
+            eprintln!(
+                "{} of {} requested frames are missing:",
+                missing.len(),
+                total
+            );
+            for time in &missing {
+                eprintln!("  {}", format_log_time(time, cli.timezone_output.as_ref()));
+            }
+        }
+        std::process::exit(1);
+    }
+}
+
+/// Where each finished frame is recorded besides the summary
+struct Records {
+    manifest: Manifest,
+    /// Feeds the --index-db writer
+    index: Option<(mpsc::UnboundedSender<IndexRecord>, JoinHandle<usize>)>,
+    /// Feeds the --event-socket writer
+    events: Option<(mpsc::Sender<FrameEvent>, JoinHandle<usize>)>,
+}
+
+/// The frames of a run once they're all done, stand-ins deduplicated and in time order
+struct Downloaded {
+    saved: Vec<(DateTime<Utc>, String)>,
+    missing: Vec<DateTime<Utc>>,
+    summary: Summary,
+}
+
+/// Downloads `frames` and records each in `records` as it finishes. Once `interrupted` is set
+/// no new frame is started. Either way the manifest is written and both writers are drained
+/// before returning, so nothing already recorded is lost on Ctrl-C
+async fn download_frames(
+    cli: &Cli,
+    client: &Client,
+    config: &Arc<FetchConfig>,
+    frames: Vec<PlannedFrame>,
+    mut records: Records,
+    interrupted: &AtomicBool,
+    progress: &ProgressBar,
+) -> Downloaded {
+    // At most max_threads are in flight. After Ctrl-C no new frame is taken and the stream
+    // ends once the in-flight ones are done
+    let total = frames.len();
+    let mut downloads = download::download_stream(
+        client.clone(),
+        config.clone(),
+        frames,
+        cli.max_threads.resolve(),
+        || !interrupted.load(Ordering::SeqCst),
+    );
+
+    if !cli.quiet {
+        // Stays hidden when stderr isn't a terminal, leaving just the log lines
+        progress.set_draw_target(ProgressDrawTarget::stderr());
+    }
+    progress.set_style(progress_style());
+    progress.set_length(total as u64);
+
+    let mut saved = Vec::new();
+    let mut missing = Vec::new();
+    let mut summary = Summary::default();
+    let mut newest = None;
+    while let Some(FinishedFrame {
+        frame,
+        result,
+        retries,
+        span,
+    }) = downloads.next().await
+    {
+        let _entered = span.enter();
+        if let Some((tx, _)) = &records.events {
+            // A full buffer means the consumer is behind, so drop rather than wait
+            let _ = tx.try_send(frame_event(&frame, &result, retries));
+        }
+        // Frames resolve here one at a time, however many are in flight
+        progress.inc(1);
+        let time = frame.timestamp;
+        records
+            .manifest
+            .entries
+            .push(ManifestEntry::new(&frame, &result, retries));
+        match result {
+            Ok(image) if image.already_present => {
+                info!("Skipped (already present) {}", image.path);
+                summary.skipped += 1;
+                saved.push((image.substituted.unwrap_or(time), image.path));
+            }
+            Ok(image) => {
+                info!("Saved image to {}", image.path);
+                summary.saved += 1;
+                summary.written_bytes += image.bytes;
+                progress.set_message(HumanBytes(summary.written_bytes).to_string());
+                // A stand-in from --nearest is recorded under the time it was taken at
+                let time = match image.substituted {
+                    Some(substituted) => {
+                        summary.substituted += 1;
+                        substituted
+                    }
+                    None => time,
+                };
+                if let Some((tx, _)) = &records.index {
+                    let _ = tx.send(IndexRecord {
+                        timestamp: time.to_rfc3339(),
+                        sat: frame.sat.url_frag().to_string(),
+                        product: frame.product.url_frag().to_string(),
+                        sector: frame.sector.url_frag().to_string(),
+                        resolution: format!("{}x{}", frame.dimensions.0, frame.dimensions.1),
+                        path: image.path.clone(),
+                        bytes: image.bytes,
+                        sha256: image.sha256.unwrap_or_default(),
+                        etag: image.etag,
+                    });
+                }
+                // Downloads finish out of order, so only ever move latest.jpg forward
+                if cli.write_latest_symlink && newest < Some(time) {
+                    newest = Some(time);
+                    if let Err(e) = update_latest(&config.options.subdirectory, &image.path) {
+                        error!("Failed to update latest.jpg: {}", e);
+                    }
+                }
+                saved.push((time, image.path));
+            }
+            Err(e) => {
+                error!("Error fetching image: {}", e);
+                if e.status() == Some(StatusCode::NOT_FOUND.as_u16()) {
+                    summary.not_found += 1;
+                }
+                missing.push(time);
+            }
+        }
+    }
+    drop(downloads);
+    progress.finish_and_clear();
+    saved.sort();
+    // A stand-in can be the same frame as a planned neighbour
+    saved.dedup();
+    missing.sort();
+    summary.failed = missing.len();
+    if let Err(e) = records
+        .manifest
+        .write(Path::new(&config.options.subdirectory))
+    {
+        error!("Failed to write {}: {}", MANIFEST_FILE, e);
+    }
+
+    if let Some((tx, writer)) = records.events {
+        drop(tx);
+        if let Ok(delivered) = writer.await {
+            info!("Delivered {} frame events", delivered);
+        }
+    }
+
+    if let Some((tx, writer)) = records.index {
+        drop(tx);
+        match writer.await {
+            Ok(indexed) => info!(
+                "Indexed {} frames in {}",
+                indexed,
+                cli.index_db.as_deref().unwrap_or_default()
+            ),
+            Err(e) => error!("Index writer panicked: {}", e),
+        }
+    }
+    Downloaded {
+        saved,
+        missing,
+        summary,
+    }
+}
+
+/// Parses a --proxy, which has to be an http:// or https:// URL with a host
+fn parse_proxy(s: &str) -> Result<url::Url, String> {
+    let url = url::Url::parse(s).map_err(|e| format!("Invalid proxy URL '{}': {}", s, e))?;
+    if !matches!(url.scheme(), "http" | "https") || url.host_str().is_none() {
+        return Err(format!(
+            "Proxy '{}' has to be an http:// or https:// URL with a host",
+            s
+        ));
+    }
+    Ok(url)
+}
+
+/// Checks the proxy variables reqwest reads, which it skips without a word when they don't
+/// parse. Like reqwest, a value without a scheme is taken as http://
+fn check_proxy_env(lookup: impl Fn(&str) -> Option<String>) -> Result<(), String> {
+    for name in [
+        "HTTPS_PROXY",
+        "https_proxy",
+        "HTTP_PROXY",
+        "http_proxy",
+        "ALL_PROXY",
+        "all_proxy",
+    ] {
+        let Some(value) = lookup(name).filter(|value| !value.is_empty()) else {
+            continue;
+        };
+        let with_scheme = if value.contains("://") {
+            value.clone()
+        } else {
+            format!("http://{}", value)
+        };
+        parse_proxy(&with_scheme).map_err(|e| format!("${}: {}", name, e))?;
+    }
+    Ok(())
+}
+
+/// Parses a finite number of degrees within -limit..=limit
+fn parse_degrees(s: &str, limit: f64) -> Result<f64, String> {
+    let degrees: f64 = s
+        .parse()
+        .map_err(|_| format!("'{}' is not a number of degrees", s))?;
+    if !degrees.is_finite() || degrees.abs() > limit {
+        return Err(format!("'{}' is outside -{}..={} degrees", s, limit, limit));
+    }
+    Ok(degrees)
+}
+
+fn parse_latitude(s: &str) -> Result<f64, String> {
+    parse_degrees(s, 90.0)
+}
+
+fn parse_longitude(s: &str) -> Result<f64, String> {
+    parse_degrees(s, 180.0)
+}
+
+/// Parses --before-sunrise or --after-sunset. Only the neighbouring days' windows are checked,
+/// so anything longer than a day couldn't reach further anyway
+fn parse_sun_offset(s: &str) -> Result<Duration, String> {
+    let offset = parse_span(s).map_err(|e| e.to_string())?;
+    if offset > Duration::days(1) {
+        return Err(format!("'{}' is longer than a day", s));
+    }
+    Ok(offset)
+}
+
+/// Parses --timeout-per-megapixel, up to an hour per megapixel
+fn parse_seconds_per_megapixel(s: &str) -> Result<f64, String> {
+    match s.parse::<f64>() {
+        Ok(seconds) if (0.0..=3600.0).contains(&seconds) => Ok(seconds),
+        Ok(_) => Err(format!("'{}' must be between 0 and 3600 seconds", s)),
+        Err(e) => Err(format!("'{}' is not a number: {}", s, e)),
+    }
+}
+
+/// Parses a --rate-limit, a number of requests per second from one a day upwards
+fn parse_rate(s: &str) -> Result<f64, String> {
+    let slowest = 1.0 / download::MAX_RATE_INTERVAL.as_secs_f64();
+    match s.parse::<f64>() {
+        Ok(rate) if rate.is_finite() && rate >= slowest => Ok(rate),
+        Ok(_) => Err(format!(
+            "'{}' must be at least one request a day ({:.2e} per second)",
+            s, slowest
+        )),
+        Err(e) => Err(format!("'{}' is not a number: {}", s, e)),
+    }
+}
+
+/// The most detailed level logged: info by default, warnings and errors only with --quiet,
+/// and debug or trace with -v or -vv
+fn log_level(verbose: u8, quiet: bool) -> Level {
+    match (quiet, verbose) {
+        (true, _) => Level::WARN,
+        (false, 0) => Level::INFO,
+        (false, 1) => Level::DEBUG,
+        (false, _) => Level::TRACE,
+    }
+}
+
+/// Logs to stderr, colored when it's a terminal. Other crates only get through with warnings,
+/// unless everything is asked for with -vv. Lines are written around `progress` so they scroll
+/// above the bar instead of tearing it
+fn init_logging(level: Level, timezone_output: Option<Tz>, progress: ProgressBar) {
+    use std::io::IsTerminal;
+    use tracing_subscriber::{filter::Targets, layer::SubscriberExt, util::SubscriberInitExt};
+
+    let dependencies = if level == Level::TRACE {
+        Level::TRACE
+    } else {
+        Level::WARN
+    };
+    tracing_subscriber::fmt()
+        .with_max_level(Level::TRACE)
+        .with_writer(move || AroundProgress(progress.clone()))
+        .with_timer(LogTimer(timezone_output))
+        .with_ansi(std::io::stderr().is_terminal())
+        .finish()
+        .with(
+            Targets::new()
+                .with_target("goesdown", level)
+                .with_default(dependencies),
+        )
+        .init();
+}
+
+/// Stamps log lines with the current time in --timezone-output, or in UTC like tracing's own
+/// timer without it
+struct LogTimer(Option<Tz>);
+
+impl tracing_subscriber::fmt::time::FormatTime for LogTimer {
+    fn format_time(&self, w: &mut tracing_subscriber::fmt::format::Writer<'_>) -> std::fmt::Result {
+        let now = Utc::now();
+        match &self.0 {
+            None => write!(w, "{}", now.format("%Y-%m-%dT%H:%M:%S%.6fZ")),
+            Some(tz) => write!(
+                w,
+                "{}",
+                now.with_timezone(tz).format("%Y-%m-%dT%H:%M:%S%.6f%:z")
+            ),
+        }
+    }
+}
+
+/// Writer for log lines that clears the progress bar while each one goes to stderr and redraws
+/// it after. The bar is shared by every worker thread, so this is safe to make from any of them
+struct AroundProgress(ProgressBar);
+
+impl std::io::Write for AroundProgress {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.suspend(|| std::io::stderr().write(buf))
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        std::io::stderr().flush()
+    }
+}
+
+/// Frames done out of the total, frames per second, the ETA and bytes written so far
+fn progress_style() -> ProgressStyle {
+    ProgressStyle::with_template(
+        "{elapsed_precise} [{wide_bar}] {pos}/{len} frames, {per_sec}, ETA {eta} {msg}",
+    )
+    .expect("the progress template is valid")
+    .progress_chars("=> ")
+}
+
+/// Stops new downloads on the first Ctrl-C so the ones in flight can finish and be moved into
+/// place. A second Ctrl-C, or one after the downloads are done, exits straight away, which at
+/// worst leaves `.jpg.tmp` files behind
+async fn watch_for_interrupt(interrupted: Arc<AtomicBool>, downloads_done: Arc<AtomicBool>) {
+    if tokio::signal::ctrl_c().await.is_err() {
+        return;
+    }
+    if downloads_done.load(Ordering::SeqCst) {
+        std::process::exit(130);
+    }
+    interrupted.store(true, Ordering::SeqCst);
+    warn!("Interrupted, waiting for downloads in flight (Ctrl-C again to quit now)");
+    if tokio::signal::ctrl_c().await.is_ok() {
+        std::process::exit(130);
+    }
+}
+
+/// Frame counts for the end-of-run rollup
+#[derive(Debug, Default)]
+struct Summary {
+    /// Downloaded this run, stand-ins included
+    saved: usize,
+    /// Kept from an earlier run by --resume
+    skipped: usize,
+    /// Missing frames --nearest replaced with an earlier one
+    substituted: usize,
+    /// Every frame that couldn't be saved, the not_found ones included
+    failed: usize,
+    /// Failed frames the CDN answered 404 for
+    not_found: usize,
+    written_bytes: u64,
+}
+
+impl Summary {
+    fn print(&self, resume: bool, nearest: bool) {
+        println!("Summary:");
+        println!("  Succeeded:   {}", self.saved);
+        if nearest {
+            println!(
+                "  Substituted: {} (earlier frame saved instead)",
+                self.substituted
+            );
+        }
+        if resume {
+            println!("  Skipped:     {} (already present)", self.skipped);
+        }
+        if self.not_found > 0 {
+            println!(
+                "  Failed:      {} ({} not found)",
+                self.failed, self.not_found
+            );
+        } else {
+            println!("  Failed:      {}", self.failed);
+        }
+        println!("  Written:     {}", format_bytes(self.written_bytes));
+    }
+}
+
+/// Renders a byte count for humans, e.g. "12.3 MiB (12897484 bytes)"
+fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+    if bytes < 1024 {
+        return format!("{} bytes", bytes);
+    }
+    let mut value = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {} ({} bytes)", value, UNITS[unit], bytes)
+}
+
+/// Prints how the downloaded images differ from `reference`, optionally saving the JSON report
+async fn compare_against(
+    subdirectory: &str,
+    reference: &str,
+    report: Option<&str>,
+) -> Result<(), String> {
+    let (new, old) = (PathBuf::from(subdirectory), PathBuf::from(reference));
+    let comparison = tokio::task::spawn_blocking(move || checksum::compare_dirs(&new, &old))
+        .await
+        .map_err(|e| format!("Comparison task panicked: {e}"))?
+        .map_err(|e| format!("Failed to compare against '{reference}': {e}"))?;
+
+    println!(
+        "Compared against {}: {} added, {} removed, {} changed, {} identical",
+        reference,
+        comparison.added.len(),
+        comparison.removed.len(),
+        comparison.changed.len(),
+        comparison.identical.len()
+    );
+    for name in &comparison.changed {
+        println!("  changed: {}", name);
+    }
+
+    if let Some(report) = report {
+        let json = serde_json::to_string_pretty(&comparison)
+            .map_err(|e| format!("Failed to serialize comparison: {e}"))?;
+        tokio::fs::write(report, json)
+            .await
+            .map_err(|e| format!("Failed to write comparison report '{report}': {e}"))?;
+    }
+    Ok(())
+}
+
+/// Atomically points `latest.jpg` in `subdirectory` at `image_path` by building the new link
+/// beside it and renaming it into place, so readers never see a missing or partial file
+fn update_latest(subdirectory: &str, image_path: &str) -> std::io::Result<()> {
+    let dir = Path::new(subdirectory);
+    let staging = dir.join(".latest.jpg.tmp");
+    let _ = std::fs::remove_file(&staging);
+    #[cfg(unix)]
+    {
+        // Relative so the directory can be moved or synced elsewhere
+        let target = Path::new(image_path).file_name().unwrap_or_default();
+        std::os::unix::fs::symlink(target, &staging)?;
+    }
+    #[cfg(not(unix))]
+    std::fs::copy(image_path, &staging)?;
+    std::fs::rename(&staging, dir.join("latest.jpg"))
+}
+
+/// Describes a finished download for --event-socket consumers
+fn frame_event(
+    frame: &PlannedFrame,
+    result: &Result<SavedImage, DownloadError>,
+    retries: u32,
+) -> FrameEvent {
+    let (image, error) = match result {
+        Ok(image) => (Some(image), None),
+        Err(e) => (None, Some(e.to_string())),
+    };
+    FrameEvent {
+        timestamp: frame.timestamp.to_rfc3339(),
+        url: frame.url.to_string(),
+        path: image.map(|image| image.path.clone()),
+        bytes: image.map(|image| image.bytes),
+        sha256: image.and_then(|image| image.sha256.clone()),
+        error,
+        retries,
+    }
+}
+
+/// Owns the index database for the whole run so every frame is written by a single writer,
+/// returning how many frames were recorded
+fn write_index(index: FrameIndex, mut records: mpsc::UnboundedReceiver<IndexRecord>) -> usize {
+    let mut indexed = 0;
+    while let Some(record) = records.blocking_recv() {
+        match index.upsert(&record) {
+            Ok(()) => indexed += 1,
+            Err(e) => error!("Failed to index {}: {}", record.path, e),
+        }
+    }
+    indexed
+}
+
+/// Rejects {dir}-style placeholders in --on-complete, pointing at the environment variable
+/// holding that value. Nothing is substituted into the command, since pasting a path into shell
+/// source breaks on spaces and lets metacharacters in it run
+fn parse_on_complete(command: &str) -> Result<String, String> {
+    for (placeholder, variable) in [
+        ("{dir}", "GOESDOWN_DIR"),
+        ("{manifest}", "GOESDOWN_MANIFEST"),
+        ("{total}", "GOESDOWN_TOTAL"),
+        ("{succeeded}", "GOESDOWN_SUCCEEDED"),
+        ("{not_found}", "GOESDOWN_NOT_FOUND"),
+        ("{failed}", "GOESDOWN_FAILED"),
+    ] {
+        if command.contains(placeholder) {
+            return Err(format!(
+                "{} isn't substituted, read the {} environment variable instead",
+                placeholder, variable
+            ));
+        }
+    }
+    Ok(command.to_string())
+}
+
+/// Runs the --on-complete command through the platform shell with `env` added to its
+/// environment
+async fn run_on_complete(
+    command: &str,
+    env: &[(&str, String)],
+) -> std::io::Result<std::process::ExitStatus> {
+    let mut shell = if cfg!(windows) {
+        let mut shell = tokio::process::Command::new("cmd");
+        shell.arg("/C");
+        shell
+    } else {
+        let mut shell = tokio::process::Command::new("sh");
+        shell.arg("-c");
+        shell
+    };
+    shell
+        .arg(command)
+        .envs(env.iter().map(|(name, value)| (name, value)))
+        .status()
+        .await
+}
+
+fn is_gif(path: &str) -> bool {
+    Path::new(path)
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("gif"))
+}
+
+/// Longest side in pixels of a GIF --timelapse
+#[cfg(feature = "imaging")]
+const TIMELAPSE_GIF_SIZE: u32 = 1024;
+
+/// Encodes `frames` (in time order) into `output`, as a GIF in-process or as a video by piping
+/// the JPEGs to ffmpeg
+async fn write_timelapse(
+    frames: Vec<(DateTime<Utc>, String)>,
+    output: &str,
+    fps: u32,
+) -> Result<(), String> {
+    if frames.is_empty() {
+        return Err("no frames were saved".to_string());
+    }
+    if is_gif(output) {
+        #[cfg(feature = "imaging")]
+        {
+            let paths: Vec<_> = frames.into_iter().map(|(_, path)| path).collect();
+            let output = PathBuf::from(output);
+            return tokio::task::spawn_blocking(move || {
+                animation::encode_gif(&paths, &output, TIMELAPSE_GIF_SIZE, fps)
+            })
+            .await
+            .map_err(|e| format!("encoder panicked: {e}"))?
+            .map_err(|e| e.to_string());
+        }
+        #[cfg(not(feature = "imaging"))]
+        unreachable!("validate_features rejects GIF timelapses without imaging");
+    }
+
+    use tokio::io::AsyncWriteExt;
+    let mut ffmpeg = tokio::process::Command::new("ffmpeg")
+        .args([
+            "-y",
+            "-loglevel",
+            "error",
+            "-f",
+            "image2pipe",
+            "-c:v",
+            "mjpeg",
+        ])
+        .args(["-framerate", &fps.to_string(), "-i", "-"])
+        // Most video encoders want even dimensions, which 339x339 thumbnails don't have
+        .args([
+            "-vf",
+            "scale=trunc(iw/2)*2:trunc(ih/2)*2,format=yuv420p",
+            output,
+        ])
+        .stdin(std::process::Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Failed to start ffmpeg (is it installed?): {e}"))?;
+    let mut stdin = ffmpeg.stdin.take().expect("stdin is piped");
+    let mut piped = Ok(());
+    for (_, path) in &frames {
+        let bytes = tokio::fs::read(path)
+            .await
+            .map_err(|e| format!("Failed to read {path}: {e}"))?;
+        if let Err(e) = stdin.write_all(&bytes).await {
+            // ffmpeg gave up, its exit status below says why
+            piped = Err(format!("Failed to pipe {path} to ffmpeg: {e}"));
+            break;
+        }
+    }
+    drop(stdin);
+    let status = ffmpeg
+        .wait()
+        .await
+        .map_err(|e| format!("Failed to wait for ffmpeg: {e}"))?;
+    if !status.success() {
+        return Err(format!("ffmpeg exited with {status}"));
+    }
+    piped
+}
+
+/// Local-vs-CDN clock difference above which pre-flight warns that frame timestamps may be off
+const CLOCK_SKEW_WARNING: TimeDelta = TimeDelta::minutes(5);
+
+/// Makes a single request to the CDN to check that it is reachable, TLS works and the local clock
+/// is close to the CDN's, returning the measured skew (local minus CDN). Skew beyond
+/// `abort_skew` is an error, otherwise large skew only warns
+async fn preflight(
+    client: &Client,
+    base_url: &url::Url,
+    abort_skew: Option<TimeDelta>,
+) -> Result<TimeDelta, String> {
+    // Goes through the client rather than probing the host directly, so --proxy, the proxy
+    // variables and --timeout apply just like they will to the downloads
+    let url = base_url.to_string();
+    let response = client.head(&url).send().await.map_err(|e| {
+        if e.is_timeout() {
+            format!("Request to {url} timed out: {e}")
+        } else if e.is_connect() {
+            format!("CDN {url} is unreachable: {e}")
+        } else {
+            // Connected but couldn't complete the request, which is almost always TLS
+            format!("TLS or HTTP failure talking to {url}: {e}")
+        }
+    })?;
+
+    let date = response
+        .headers()
+        .get(reqwest::header::DATE)
+        .and_then(|value| value.to_str().ok())
+        .ok_or_else(|| format!("{url} did not return a Date header to check the clock against"))?;
+    let cdn_time = DateTime::parse_from_rfc2822(date)
+        .map_err(|e| format!("Unparseable Date header '{date}' from {url}: {e}"))?;
+    let skew = Utc::now() - cdn_time.with_timezone(&Utc);
+    if let Some(limit) = abort_skew.filter(|limit| skew.abs() > *limit) {
+        return Err(format!(
+            "Local clock differs from the CDN by {}s (limit {}s), frame timestamps would be wrong",
+            skew.num_seconds(),
+            limit.num_seconds()
+        ));
+    }
+    if skew.abs() > CLOCK_SKEW_WARNING {
+        warn!(
+            "local clock differs from the CDN by {}s, frame timestamps may be wrong",
+            skew.num_seconds()
+        );
+    }
+    Ok(skew)
+}
+
+/// Cell size in pixels of a --contact-sheet, in case the sector had no thumbnail size
+#[cfg(feature = "imaging")]
+const CONTACT_SHEET_CELL: u32 = 339;
+
+/// Size in pixels of the longest side of each hourly preview GIF frame
+#[cfg(feature = "imaging")]
+const PREVIEW_SIZE: u32 = 480;
+#[cfg(feature = "imaging")]
+const PREVIEW_FPS: u32 = 4;
+
+/// Groups saved frames by the UTC hour they fall in, keeping each hour's frames in time order
+#[cfg(feature = "imaging")]
+fn bucket_by_hour(
+    frames: Vec<(DateTime<Utc>, String)>,
+) -> BTreeMap<DateTime<Utc>, Vec<(DateTime<Utc>, String)>> {
+    let mut buckets: BTreeMap<_, Vec<_>> = BTreeMap::new();
+    for (time, path) in frames {
+        let hour = time
+            .with_minute(0)
+            .and_then(|t| t.with_second(0))
+            .and_then(|t| t.with_nanosecond(0))
+            .unwrap();
+        buckets.entry(hour).or_default().push((time, path));
+    }
+    for frames in buckets.values_mut() {
+        frames.sort();
+    }
+    buckets
+}
+
+/// Encodes one GIF per hour of saved frames and an index.html linking them, returning the path of
+/// the index
+#[cfg(feature = "imaging")]
+async fn write_hourly_previews(
+    frames: Vec<(DateTime<Utc>, String)>,
+    config: &FetchConfig,
+) -> Result<String, String> {
+    let mut entries = Vec::new();
+    for (hour, frames) in bucket_by_hour(frames) {
+        let gif_name = format!(
+            "preview_{}.gif",
+            format_output_time(&hour, config.options.timezone_output.as_ref())
+        );
+        let gif_path = Path::new(&config.options.subdirectory).join(&gif_name);
+        let paths: Vec<String> = frames.into_iter().map(|(_, path)| path).collect();
+        let frame_count = paths.len();
+        let output = gif_path.clone();
+        tokio::task::spawn_blocking(move || {
+            animation::encode_gif(&paths, &output, PREVIEW_SIZE, PREVIEW_FPS)
+        })
+        .await
+        .map_err(|e| format!("Preview task panicked: {e}"))?
+        .map_err(|e| format!("Failed to write {}: {e}", gif_path.display()))?;
+        info!("Wrote {} ({} frames)", gif_path.display(), frame_count);
+        entries.push(format!(
+            "<h2>{label}</h2>\n<p>{frame_count} frames</p>\n<img src=\"{gif_name}\" alt=\"{label}\">\n",
+            label = format_log_time(&hour, config.options.timezone_output.as_ref())
+        ));
+    }
+
+    let index_path = Path::new(&config.options.subdirectory).join("index.html");
+    let html = format!(
+        "<!DOCTYPE html>\n<html>\n<head><meta charset=\"utf-8\"><title>Hourly previews</title></head>\n<body>\n{}</body>\n</html>\n",
+        entries.concat()
+    );
+    tokio::fs::write(&index_path, html)
+        .await
+        .map_err(|e| format!("Failed to write {}: {e}", index_path.display()))?;
+    Ok(index_path.display().to_string())
+}
+
+/// Downloads NOAA's ready-made loop into the root directory, prefixed with the download time
+/// since the same URL is regenerated in place
+async fn fetch_noaa_animation(client: &Client, cli: &Cli) -> Result<String, String> {
+    let url = goesimages::construct_animation_url_on(&cli.base_url, &cli.sat)
+        .map_err(|e| format!("Failed to construct animation url: {e}"))?;
+
+    let response = send_with_reconnect(client, &url)
+        .await
+        .map_err(|e| format!("Failed to fetch {url}: {e}"))?;
+
+    if !response.status().is_success() {
+        return Err(format!("Failed to fetch {url}: HTTP{}", response.status()));
+    }
+
+    let source_name = url_file_name(&url).unwrap_or("animation.gif");
+    let path = Path::new(&cli.root).join(format!(
+        "{}_{source_name}",
+        format_output_time(&Utc::now(), cli.timezone_output.as_ref())
+    ));
+    let bytes = response
+        .bytes()
+        .await
+        .map_err(|e| format!("Failed to read response: {e}"))?;
+    tokio::fs::write(&path, bytes)
+        .await
+        .map_err(|e| format!("Failed to save animation: {e}"))?;
+
+    Ok(path.display().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    #[test]
+    fn ago_resolves_against_fixed_now() -> Result<(), String> {
+        let cli = Cli::try_parse_from(["goesdown", "--now", "2024-11-30T12:34:56Z", "--ago", "1h"])
+            .map_err(|e| e.to_string())?;
+        let range = cli.validate_and_parse()?;
+        assert_eq!("2024-11-30 11:30:00 UTC", range.start.to_string());
+        assert_eq!("2024-11-30 12:30:00 UTC", range.end.to_string());
+        Ok(())
+    }
+
+    #[test]
+    fn since_manifest_continues_after_the_newest_saved_frame() -> Result<(), String> {
+        let dir = tempfile::tempdir().map_err(|e| e.to_string())?;
+        let path = dir.path().join(MANIFEST_FILE);
+        let range = |json: &str| {
+            std::fs::write(&path, json).unwrap();
+            let path = path.to_str().unwrap();
+            let args = [
+                "goesdown",
+                "--now",
+                "2024-11-30T12:34:56Z",
+                "--since-manifest",
+                path,
+            ];
+            Cli::try_parse_from(args).unwrap().validate_and_parse()
+        };
+        let manifest = |entries: &str| {
+            format!(
+                r#"{{"sat": "GOES16", "product": "GEOCOLOR", "sector": "FD",
+                "resolution": "1808x1808", "start": "2024-11-30T10:00:00+00:00",
+                "end": "2024-11-30T11:00:00+00:00", "stride_minutes": 10, "entries": [{}]}}"#,
+                entries
+            )
+        };
+        let entry = |time: &str, error: &str| {
+            format!(
+                r#"{{"timestamp": "{}", "url": "https://example.com/a.jpg",
+                "file_name": "a.jpg"{}}}"#,
+                time, error
+            )
+        };
+
+        // The failed 11:00 frame is tried again
+        let entries = [
+            entry("2024-11-30T10:40:00+00:00", ""),
+            entry("2024-11-30T10:50:00+00:00", ""),
+            entry(
+                "2024-11-30T11:00:00+00:00",
+                r#", "error": "HTTP404 Not Found""#,
+            ),
+        ];
+        let continued = range(&manifest(&entries.join(",")))?;
+        assert_eq!("2024-11-30 11:00:00 UTC", continued.start.to_string());
+        assert_eq!("2024-11-30 12:30:00 UTC", continued.end.to_string());
+
+        let up_to_date = entry("2024-11-30T12:30:00+00:00", "");
+        assert!(range(&manifest(&up_to_date)).is_err());
+        let error = |json: &str| range(json).err().unwrap();
+        assert!(error(&manifest("")).contains("doesn't record any saved frames"));
+        assert!(error("[]").contains("isn't a goesdown manifest"));
+        Ok(())
+    }
+
+    #[test]
+    fn huge_spans_are_errors() {
+        let range = |args: &[&str]| {
+            let base = ["goesdown", "--now", "2024-11-30T12:00:00Z"];
+            Cli::try_parse_from(base.iter().chain(args))
+                .unwrap()
+                .validate_and_parse()
+                .map(|_| ())
+        };
+        assert!(range(&["--ago", "200000000000d"]).is_err());
+        assert!(range(&["--ago", "100000000d"]).is_err());
+        assert!(range(&["--ago", "1h", "--duration", "20000000000000m"]).is_err());
+        assert!(range(&["--ago", "1h", "--skip-future-within", "100000000d"]).is_err());
+        assert!(range(&["--ago", "1h", "--stride", "200000000000000"]).is_err());
+        assert!(range(&["--ago", "1h", "--stride", "1000000000000"]).is_err());
+        assert!(range(&["--ago", "1h", "--stride", "7210"]).is_err());
+        assert!(range(&["--ago", "1h", "--stride", "7200"]).is_ok());
+
+        let start = Utc.with_ymd_and_hms(2024, 11, 30, 12, 0, 0).unwrap();
+        assert_eq!(
+            vec![start],
+            download::frame_times(start, DateTime::<Utc>::MAX_UTC, i64::MAX).collect::<Vec<_>>()
+        );
+        assert_eq!(
+            1,
+            download::frame_times(start, DateTime::<Utc>::MAX_UTC, 1000000000000).count()
+        );
+    }
+
+    #[test]
+    fn start_with_seconds_snaps_to_minute() -> Result<(), String> {
+        let cli = Cli::try_parse_from([
+            "goesdown",
+            "--now",
+            "2024-11-30T12:00:00Z",
+            "--start",
+            "2024-11-30T11:20:30.5Z",
+        ])
+        .map_err(|e| e.to_string())?;
+        let range = cli.validate_and_parse()?;
+        assert_eq!("2024-11-30 11:20:00 UTC", range.start.to_string());
+        Ok(())
+    }
+
+    #[test]
+    fn start_snaps_to_sector_cadence() -> Result<(), String> {
+        let start = |args: &[&str]| -> Result<String, String> {
+            let base = ["goesdown", "--now", "2024-11-30T13:00:00Z"];
+            let cli = Cli::try_parse_from(base.iter().chain(args)).map_err(|e| e.to_string())?;
+            Ok(cli.validate_and_parse()?.start.to_string())
+        };
+        assert_eq!(
+            "2024-11-30 12:00:00 UTC",
+            start(&["--start", "2024-11-30T12:03:00Z"])?
+        );
+        let conus = [
+            "--start",
+            "2024-11-30T12:37:00Z",
+            "--sector",
+            "conus",
+            "--stride",
+            "5",
+        ];
+        assert_eq!("2024-11-30 12:35:00 UTC", start(&conus)?);
+        let meso = [
+            "--start",
+            "2024-11-30T12:37:00Z",
+            "--sector",
+            "meso1",
+            "--stride",
+            "1",
+        ];
+        assert_eq!("2024-11-30 12:37:00 UTC", start(&meso)?);
+        Ok(())
+    }
+
+    #[test]
+    fn job_file_fills_missing_flags() -> Result<(), String> {
+        let matches = Cli::command()
+            .try_get_matches_from(["goesdown", "--stride", "20", "--sector", "conus"])
+            .map_err(|e| e.to_string())?;
+        let mut cli = Cli::from_arg_matches(&matches).map_err(|e| e.to_string())?;
+        let job: JobFile = serde_json::from_str(
+            r#"{"ago": "2h", "stride": 30, "max_threads": 2, "sat": "west", "product": "AirMass",
+                "sector": "meso1", "resolution": "678x678"}"#,
+        )
+        .map_err(|e| e.to_string())?;
+        job.apply(&mut cli, &matches);
+        assert_eq!(Some("2h".to_string()), cli.ago);
+        assert_eq!(20, cli.stride);
+        assert_eq!(MaxThreads::Fixed(2), cli.max_threads);
+        assert_eq!(goesimages::Sat::GoesWest, cli.sat);
+        assert_eq!(goesimages::Product::AirMass, cli.product);
+        assert_eq!(goesimages::Sector::Conus, cli.sector);
+        assert_eq!(goesimages::Resolution::Low, cli.resolution());
+        assert!(serde_json::from_str::<JobFile>(r#"{"sat": "goes99"}"#).is_err());
+
+        let end = r#"{"start": "2024-11-30T11:00:00Z", "end": "2024-11-30T12:00:00Z"}"#;
+        let job: JobFile = serde_json::from_str(end).map_err(|e| e.to_string())?;
+        job.validate()?;
+        job.apply(&mut cli, &matches);
+        assert_eq!(Some("2024-11-30T12:00:00Z".to_string()), cli.end);
+        let matches = Cli::command()
+            .try_get_matches_from(["goesdown", "--duration", "30m"])
+            .map_err(|e| e.to_string())?;
+        let mut cli = Cli::from_arg_matches(&matches).map_err(|e| e.to_string())?;
+        let job: JobFile = serde_json::from_str(end).map_err(|e| e.to_string())?;
+        job.apply(&mut cli, &matches);
+        assert_eq!((Some("30m".to_string()), None), (cli.duration, cli.end));
+        let both = r#"{"ago": "2h", "duration": "1h", "end": "2024-11-30T12:00:00Z"}"#;
+        let job: JobFile = serde_json::from_str(both).map_err(|e| e.to_string())?;
+        assert!(job.validate().is_err());
+        assert!(serde_json::from_str::<JobFile>(r#"{"strid": 30}"#).is_err());
+
+        let threads = |json: &str| serde_json::from_str::<JobFile>(json).map(|job| job.max_threads);
+        assert_eq!(
+            Some(MaxThreads::Auto),
+            threads(r#"{"max_threads": "auto"}"#).map_err(|e| e.to_string())?
+        );
+        assert!(threads(r#"{"max_threads": 0}"#)
+            .unwrap_err()
+            .to_string()
+            .contains("at least 1"));
+        assert!(threads(r#"{"max_threads": -2}"#).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn skip_future_within_drops_trailing_frames() -> Result<(), String> {
+        let cli = Cli::try_parse_from([
+            "goesdown",
+            "--now",
+            "2024-11-30T12:05:00Z",
+            "--ago",
+            "1h",
+            "--skip-future-within",
+        ])
+        .map_err(|e| e.to_string())?;
+        let plan = cli.planned_times(&cli.validate_and_parse()?);
+        assert_eq!(1, plan.unpublished_frames);
+        assert_eq!(
+            Some(&Utc.with_ymd_and_hms(2024, 11, 30, 11, 50, 0).unwrap()),
+            plan.times.last()
+        );
+
+        let unpublished = |args: &[&str]| -> Result<usize, String> {
+            let base = ["goesdown", "--now", "2024-11-30T12:05:00Z", "--ago", "1h"];
+            let cli = Cli::try_parse_from(base.iter().chain(args)).map_err(|e| e.to_string())?;
+            Ok(cli
+                .planned_times(&cli.validate_and_parse()?)
+                .unpublished_frames)
+        };
+        // Windows off the 10 minute grid are fine
+        assert_eq!(Ok(1), unpublished(&["--skip-future-within", "15m"]));
+        assert_eq!(Ok(2), unpublished(&["--skip-future-within", "16m"]));
+        // Without a value the window is one cadence of the sector, 5 minutes for conus
+        let conus = ["--sector", "conus", "--stride", "5", "--skip-future-within"];
+        assert_eq!(Ok(1), unpublished(&conus));
+        Ok(())
+    }
+
+    #[test]
+    fn sun_window_keeps_frames_around_daylight() -> Result<(), String> {
+        // Denver, where the sun rises around 11:30Z and sets around 02:30Z the next day
+        let cli = Cli::try_parse_from([
+            "goesdown",
+            "--now",
+            "2024-06-22T06:00:00Z",
+            "--ago",
+            "1d",
+            "--stride",
+            "60",
+            "--lat",
+            "39.7",
+            "--lon",
+            "-105",
+            "--before-sunrise",
+            "1h",
+            "--after-sunset",
+            "1h",
+        ])
+        .map_err(|e| e.to_string())?;
+        let plan = cli.planned_times(&cli.validate_and_parse()?);
+        assert_eq!(17, plan.times.len());
+        assert_eq!(8, plan.outside_sun_window);
+        assert_eq!(
+            Some(&Utc.with_ymd_and_hms(2024, 6, 21, 11, 0, 0).unwrap()),
+            plan.times.first()
+        );
+        assert_eq!(
+            Some(&Utc.with_ymd_and_hms(2024, 6, 22, 3, 0, 0).unwrap()),
+            plan.times.last()
+        );
+        assert!(plan.polar_dates.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn sat_selects_satellite() -> Result<(), String> {
+        let time = Utc.with_ymd_and_hms(2024, 11, 30, 8, 30, 0).unwrap();
+        let east = Cli::try_parse_from(["goesdown", "--ago", "1h"]).map_err(|e| e.to_string())?;
+        let west = Cli::try_parse_from(["goesdown", "--ago", "1h", "--sat", "West"])
+            .map_err(|e| e.to_string())?;
+        assert!(Cli::try_parse_from(["goesdown", "--ago", "1h", "--sat", "north"]).is_err());
+
+        let east_url = east.plan_options(String::new()).frame_url(&time).unwrap();
+        let west_url = west.plan_options(String::new()).frame_url(&time).unwrap();
+        assert!(east_url.as_str().contains("/GOES16/"));
+        assert!(west_url.as_str().contains("/GOES18/"));
+        assert!(west
+            .subdirectory_name(time, time)?
+            .starts_with("images_GOES18_"));
+        Ok(())
+    }
+
+    #[test]
+    fn subdirectory_names_tell_runs_apart() -> Result<(), String> {
+        let time = Utc.with_ymd_and_hms(2024, 11, 30, 8, 30, 0).unwrap();
+        let name = |args: &[&str]| -> Result<String, String> {
+            let base = ["goesdown", "--ago", "1h"];
+            let cli = Cli::try_parse_from(base.iter().chain(args)).map_err(|e| e.to_string())?;
+            cli.subdirectory_name(time, time)
+        };
+        let geocolor = name(&[])?;
+        assert_eq!(
+            "images_GOES16_FD_GEOCOLOR_1808x1808_20241130T083000_to_20241130T083000_stride_10m",
+            geocolor
+        );
+        let names = BTreeSet::from([
+            geocolor,
+            name(&["--product", "band13"])?,
+            name(&["--resolution", "high"])?,
+            name(&["--thumbnails"])?,
+        ]);
+        assert_eq!(4, names.len());
+        Ok(())
+    }
+
+    #[test]
+    fn stride_follows_sector_cadence() {
+        let planned = |args: &[&str]| -> Result<usize, String> {
+            let base = ["goesdown", "--now", "2024-11-30T12:05:00Z", "--ago", "10m"];
+            let cli = Cli::try_parse_from(base.iter().chain(args)).map_err(|e| e.to_string())?;
+            Ok(cli.planned_times(&cli.validate_and_parse()?).times.len())
+        };
+        assert!(planned(&["--stride", "5"]).is_err());
+        assert_eq!(Ok(3), planned(&["--stride", "5", "--sector", "conus"]));
+        assert_eq!(Ok(11), planned(&["--stride", "1", "--sector", "meso1"]));
+        // Durations only have to line up with the sector's cadence too
+        assert!(planned(&["--duration", "3m"]).is_err());
+        let meso = ["--duration", "3m", "--stride", "1", "--sector", "meso1"];
+        assert_eq!(Ok(4), planned(&meso));
+    }
+
+    #[test]
+    fn dry_run_leaves_filesystem_untouched() -> Result<(), String> {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        let cli = Cli::try_parse_from([
+            "goesdown",
+            "--now",
+            "2024-11-30T12:05:00Z",
+            "--ago",
+            "1h",
+            "--dry-run",
+            "--root",
+            root.to_str().unwrap(),
+        ])
+        .map_err(|e| e.to_string())?;
+        let plan = cli.dry_run_plan()?;
+        assert_eq!(7, plan.entries.len());
+        assert!(plan.entries[0]
+            .url
+            .as_str()
+            .ends_with("20243351100_GOES16-ABI-FD-GEOCOLOR-1808x1808.jpg"));
+        assert_eq!(0, std::fs::read_dir(root).unwrap().count());
+        Ok(())
+    }
+
+    #[test]
+    fn overwrite_refuses_unexpected_files() -> Result<(), String> {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        let args = |extra: &[&str]| -> Result<Cli, String> {
+            let mut args = vec![
+                "goesdown",
+                "--now",
+                "2024-11-30T12:05:00Z",
+                "--ago",
+                "1h",
+                "--root",
+                root.to_str().unwrap(),
+            ];
+            args.extend_from_slice(extra);
+            Cli::try_parse_from(args).map_err(|e| e.to_string())
+        };
+        let cli = args(&[])?;
+        let range = cli.validate_and_parse()?;
+        let (subdirectory, _) = cli.validate_directory(range.start, range.end)?;
+        std::fs::write(
+            Path::new(&subdirectory).join("20241130T110000.jpg"),
+            b"jpeg",
+        )
+        .unwrap();
+
+        assert!(cli.subdirectory_path(range.start, range.end).is_err());
+        let overwrite = args(&["--overwrite"])?;
+        assert!(matches!(
+            overwrite.subdirectory_path(range.start, range.end),
+            Ok((_, true))
+        ));
+
+        std::fs::write(Path::new(&subdirectory).join("notes.txt"), b"mine").unwrap();
+        let e = overwrite
+            .subdirectory_path(range.start, range.end)
+            .unwrap_err();
+        assert!(e.contains("notes.txt"), "{e}");
+        std::fs::remove_file(Path::new(&subdirectory).join("notes.txt")).unwrap();
+        // Frames from a run with a different extension are recognized by the template
+        std::fs::write(
+            Path::new(&subdirectory).join("20241130T110000.jpeg"),
+            b"jpeg",
+        )
+        .unwrap();
+        assert!(overwrite.subdirectory_path(range.start, range.end).is_err());
+        let jpeg = args(&["--overwrite", "--name-template", "{time}.jpeg"])?;
+        assert!(jpeg.subdirectory_path(range.start, range.end).is_ok());
+        std::fs::write(Path::new(&subdirectory).join("notes.txt"), b"mine").unwrap();
+
+        let forced = args(&["--overwrite", "--force"])?;
+        assert!(forced.subdirectory_path(range.start, range.end).is_ok());
+        assert!(args(&["--overwrite", "--resume"]).is_err());
+        assert!(args(&["--force"]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn parses_local_and_partial_start_times() -> Result<(), String> {
+        let chicago = Some(&chrono_tz::America::Chicago);
+        let expected = Utc.with_ymd_and_hms(2024, 11, 30, 18, 0, 0).unwrap();
+        for input in [
+            "2024-11-30T18:00:00Z",
+            "2024-11-30T12:00:00-06:00",
+            "2024-11-30T12:00:00",
+            "2024-11-30 12:00:00.000",
+            "2024-11-30T12:00",
+            "2024-11-30 12:00",
+        ] {
+            assert_eq!(expected, parse_start_time(input, chicago)?, "{input}");
+        }
+        assert_eq!(
+            Utc.with_ymd_and_hms(2024, 11, 30, 6, 0, 0).unwrap(),
+            parse_start_time("2024-11-30", chicago)?
+        );
+        // 2am doesn't happen on the day clocks spring forward
+        assert!(parse_start_time("2024-03-10T02:30:00", chicago).is_err());
+        let e = parse_start_time("yesterday", chicago).unwrap_err();
+        assert!(e.contains(TIME_FORMATS), "{e}");
+        Ok(())
+    }
+
+    #[test]
+    fn validates_proxies() {
+        assert!(parse_proxy("http://proxy.example:3128").is_ok());
+        assert!(parse_proxy("proxy.example:3128").is_err());
+        assert!(parse_proxy("socks5://proxy.example:1080").is_err());
+
+        let env = |value: &'static str| {
+            move |name: &str| (name == "https_proxy").then(|| value.to_string())
+        };
+        assert!(check_proxy_env(env("proxy.example:3128")).is_ok());
+        assert!(check_proxy_env(env("https://user:pw@proxy.example")).is_ok());
+        let e = check_proxy_env(env("http://[bad")).unwrap_err();
+        assert!(e.starts_with("$https_proxy: "), "{e}");
+        assert!(check_proxy_env(|_| None).is_ok());
+    }
+
+    #[test]
+    fn coordinates_must_be_on_earth() {
+        let at = |lat: &str, lon: &str| {
+            Cli::try_parse_from(["goesdown", "--lat", lat, "--lon", lon])
+                .map(|cli| (cli.lat, cli.lon))
+        };
+        assert_eq!((Some(-90.0), Some(180.0)), at("-90", "180").unwrap());
+        assert!(at("400", "0").is_err());
+        assert!(at("NaN", "0").is_err());
+        assert!(at("0", "1e300").is_err());
+        assert!(at("0", "-inf").is_err());
+    }
+
+    #[test]
+    fn sun_offsets_are_at_most_a_day() {
+        let sun = |offset: &str| {
+            let args = [
+                "goesdown",
+                "--lat",
+                "10",
+                "--lon",
+                "0",
+                "--before-sunrise",
+                offset,
+            ];
+            Cli::try_parse_from(args).map(|cli| cli.before_sunrise)
+        };
+        assert_eq!(Some(Duration::hours(24)), sun("1d").unwrap());
+        assert!(sun("1d1s").is_err());
+        assert!(sun("9000000000000s").is_err());
+    }
+
+    #[test]
+    fn rate_limits_stay_in_range() {
+        assert_eq!(Ok(0.5), parse_rate("0.5"));
+        assert!(parse_rate("0.00002").is_ok());
+        for bad in ["0", "-1", "1e-12", "1e-30", "inf", "NaN", "fast"] {
+            assert!(parse_rate(bad).is_err(), "{bad}");
+        }
+        assert!(Cli::try_parse_from(["goesdown", "--delay", "86400001"]).is_err());
+    }
+
+    #[test]
+    fn log_timestamps_follow_timezone_output() {
+        use tracing_subscriber::fmt::{format::Writer, time::FormatTime};
+
+        let stamp = |tz: Option<Tz>| {
+            let mut line = String::new();
+            LogTimer(tz)
+                .format_time(&mut Writer::new(&mut line))
+                .unwrap();
+            line
+        };
+        assert!(stamp(None).ends_with('Z'));
+        assert!(stamp(Some(chrono_tz::Asia::Tokyo)).ends_with("+09:00"));
+    }
+
+    #[test]
+    fn verbosity_picks_log_level() {
+        let level = |args: &[&str]| {
+            let cli = Cli::try_parse_from(["goesdown"].iter().chain(args)).unwrap();
+            log_level(cli.verbose, cli.quiet)
+        };
+        assert_eq!(Level::INFO, level(&[]));
+        assert_eq!(Level::DEBUG, level(&["-v"]));
+        assert_eq!(Level::TRACE, level(&["-vv"]));
+        assert_eq!(Level::WARN, level(&["--quiet"]));
+        assert!(Cli::try_parse_from(["goesdown", "-q", "-v"]).is_err());
+    }
+
+    #[test]
+    fn formats_byte_counts() {
+        assert_eq!("512 bytes", format_bytes(512));
+        assert_eq!("1.5 KiB (1536 bytes)", format_bytes(1536));
+        assert_eq!("12.3 MiB (12897484 bytes)", format_bytes(12_897_484));
+    }
+
+    #[test]
+    fn parses_max_threads() {
+        assert_eq!(Ok(MaxThreads::Fixed(4)), "4".parse());
+        assert_eq!(Ok(MaxThreads::Auto), "Auto".parse());
+        assert!("0".parse::<MaxThreads>().is_err());
+        assert!("lots".parse::<MaxThreads>().is_err());
+        assert!((1..=MAX_AUTO_THREADS).contains(&MaxThreads::Auto.resolve()));
+    }
+
+    #[test]
+    #[cfg(feature = "imaging")]
+    fn frames_bucket_by_hour() {
+        let at = |h, m| Utc.with_ymd_and_hms(2024, 11, 30, h, m, 0).unwrap();
+        let buckets = bucket_by_hour(vec![
+            (at(9, 10), "c".to_string()),
+            (at(8, 50), "b".to_string()),
+            (at(8, 0), "a".to_string()),
+        ]);
+        let hours: Vec<_> = buckets.keys().copied().collect();
+        assert_eq!(vec![at(8, 0), at(9, 0)], hours);
+        assert_eq!(
+            vec![(at(8, 0), "a".to_string()), (at(8, 50), "b".to_string())],
+            buckets[&at(8, 0)]
+        );
+    }
+
+    #[tokio::test]
+    async fn interrupted_run_still_records_finished_frames() -> Result<(), String> {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+        let db = dir.join("index.db");
+        let interrupted = Arc::new(AtomicBool::new(false));
+
+        // Serves a frame to every request and presses Ctrl-C as the second one arrives
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        let flag = interrupted.clone();
+        tokio::spawn(async move {
+            for request in 1.. {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                if request == 2 {
+                    flag.store(true, Ordering::SeqCst);
+                }
+                let _ = socket.read(&mut [0; 4096]).await;
+                let _ = socket
+                    .write_all(
+                        b"HTTP/1.1 200 OK\r\nContent-Type: image/jpeg\r\nContent-Length: 4\r\n\
+                          Connection: close\r\n\r\n\xFF\xD8\xFF\xD9",
+                    )
+                    .await;
+            }
+        });
+
+        let cli = Cli::try_parse_from([
+            "goesdown",
+            "--now",
+            "2024-11-30T12:00:00Z",
+            "--ago",
+            "1h",
+            "--max-threads",
+            "1",
+            "--base-url",
+            &base,
+            "--index-db",
+            db.to_str().unwrap(),
+        ])
+        .map_err(|e| e.to_string())?;
+        let range = cli.validate_and_parse()?;
+        let config = Arc::new(cli.fetch_config(dir.to_string_lossy().to_string(), None));
+        let frames = plan::plan(&config.options, cli.planned_times(&range).times)?.entries;
+        assert_eq!(7, frames.len());
+        let index = FrameIndex::open(&db).map_err(|e| e.to_string())?;
+        let (tx, rx) = mpsc::unbounded_channel();
+        let records = Records {
+            manifest: Manifest::new(
+                &config.options,
+                frames[0].dimensions,
+                range.start,
+                range.end,
+                10,
+            ),
+            index: Some((
+                tx,
+                tokio::task::spawn_blocking(move || write_index(index, rx)),
+            )),
+            events: None,
+        };
+        let downloaded = download_frames(
+            &cli,
+            &cli.build_client()?,
+            &config,
+            frames,
+            records,
+            &interrupted,
+            &ProgressBar::hidden(),
+        )
+        .await;
+
+        // The frame in flight at the interrupt finishes, nothing after it starts, and both are
+        // in the manifest and the index by the time the run would exit
+        assert_eq!(2, downloaded.summary.saved);
+        let manifest: serde_json::Value =
+            serde_json::from_slice(&std::fs::read(dir.join(MANIFEST_FILE)).unwrap()).unwrap();
+        assert_eq!(2, manifest["entries"].as_array().unwrap().len());
+        let indexed: u32 = rusqlite::Connection::open(&db)
+            .and_then(|conn| conn.query_row("SELECT COUNT(*) FROM frames", [], |row| row.get(0)))
+            .map_err(|e| e.to_string())?;
+        assert_eq!(2, indexed);
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn on_complete_gets_values_from_the_environment() {
+        let dir = "out dir; $(touch pwned) 'quoted'";
+        let env = [("GOESDOWN_DIR", dir.to_string())];
+        let status = run_on_complete(
+            r#"test "$GOESDOWN_DIR" = "out dir; \$(touch pwned) 'quoted'""#,
+            &env,
+        )
+        .await
+        .unwrap();
+        assert!(status.success());
+        assert!(!Path::new("pwned").exists());
+        assert!(parse_on_complete("ls {dir}").is_err());
+        assert!(parse_on_complete("jq . {manifest}")
+            .unwrap_err()
+            .contains("GOESDOWN_MANIFEST"));
+        assert!(Cli::try_parse_from(["goesdown", "--on-complete", "ls {dir}"]).is_err());
+    }
 }
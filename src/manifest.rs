@@ -0,0 +1,255 @@
+use std::path::Path;
+
+use chrono::{DateTime, Utc};
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+use crate::download::{DownloadError, SavedImage};
+use crate::plan::{PlanOptions, PlannedFrame};
+
+/// File name of the manifest written into each run's subdirectory
+pub const MANIFEST_FILE: &str = "manifest.json";
+
+/// Machine-readable record of a run: what was asked for and what happened to every frame
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct Manifest {
+    /// Satellite as requested, like "east". Which one that was for each frame is in its entry
+    pub sat: String,
+    pub product: String,
+    pub sector: String,
+    /// `WIDTHxHEIGHT` of the requested resolution
+    pub resolution: String,
+    /// Range bounds in RFC 3339
+    pub start: String,
+    pub end: String,
+    pub stride_minutes: i64,
+    /// One entry per planned frame, in time order
+    pub entries: Vec<ManifestEntry>,
+}
+
+/// Outcome of one planned frame. Failed frames keep their timestamp and URL with the error in
+/// place of the size, so expected and actual timestamps can be diffed
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct ManifestEntry {
+    /// Frame time in RFC 3339
+    pub timestamp: String,
+    /// CDN name of the satellite the frame is from, like "GOES16". A range across the GOES-19
+    /// handover has both
+    #[serde(default)]
+    pub sat: String,
+    pub url: String,
+    pub file_name: String,
+    /// Time of the earlier frame saved in place of this missing one by --nearest, in RFC 3339.
+    /// `file_name` is then that frame's
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub substituted: Option<String>,
+    /// HTTP status of the response, absent when nothing was received or the frame was kept
+    /// from an earlier run
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bytes: Option<u64>,
+    /// The final error of a frame that couldn't be saved
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Attempts after the first, absent when the first one settled it
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub retries: u32,
+}
+
+fn is_zero(n: &u32) -> bool {
+    *n == 0
+}
+
+impl ManifestEntry {
+    pub fn new(
+        frame: &PlannedFrame,
+        result: &Result<SavedImage, DownloadError>,
+        retries: u32,
+    ) -> ManifestEntry {
+        let (status, bytes, error) = match result {
+            Ok(image) => (image.status, Some(image.bytes), None),
+            Err(e) => (e.status(), None, Some(e.to_string())),
+        };
+        let substitute = result
+            .as_ref()
+            .ok()
+            .filter(|image| image.substituted.is_some());
+        let file_name = match substitute {
+            Some(image) => Path::new(&image.path)
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_default(),
+            None => frame.file_name.clone(),
+        };
+        ManifestEntry {
+            timestamp: frame.timestamp.to_rfc3339(),
+            sat: frame.sat.url_frag().to_string(),
+            url: frame.url.to_string(),
+            file_name,
+            substituted: substitute
+                .and_then(|image| image.substituted)
+                .map(|time| time.to_rfc3339()),
+            status,
+            bytes,
+            error,
+            retries,
+        }
+    }
+}
+
+impl Manifest {
+    /// Starts an empty manifest for a run of `options` over `start..=end`
+    pub fn new(
+        options: &PlanOptions,
+        dimensions: (u32, u32),
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+        stride_minutes: i64,
+    ) -> Manifest {
+        Manifest {
+            sat: options
+                .sat
+                .to_possible_value()
+                .map(|value| value.get_name().to_string())
+                .unwrap_or_default(),
+            product: options.product.url_frag().to_string(),
+            sector: options.sector.url_frag().to_string(),
+            resolution: format!("{}x{}", dimensions.0, dimensions.1),
+            start: start.to_rfc3339(),
+            end: end.to_rfc3339(),
+            stride_minutes,
+            entries: Vec::new(),
+        }
+    }
+
+    /// Reads a manifest written by an earlier run
+    pub fn read(path: &Path) -> Result<Manifest, String> {
+        let json =
+            std::fs::read(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        serde_json::from_slice(&json)
+            .map_err(|e| format!("{} isn't a goesdown manifest: {}", path.display(), e))
+    }
+
+    /// Time of the newest frame the manifest records as saved, substituted ones aside
+    pub fn newest_saved(&self) -> Option<DateTime<Utc>> {
+        self.entries
+            .iter()
+            .filter(|entry| entry.error.is_none() && entry.substituted.is_none())
+            .filter_map(|entry| DateTime::parse_from_rfc3339(&entry.timestamp).ok())
+            .map(|time| time.with_timezone(&Utc))
+            .max()
+    }
+
+    /// Writes the manifest as pretty JSON to `dir`, sorting the entries by time first since
+    /// downloads finish out of order. The file is staged beside its final name and renamed into
+    /// place so a reader never sees it half written
+    pub fn write(&mut self, dir: &Path) -> std::io::Result<()> {
+        // RFC 3339 strings from the same timezone sort chronologically
+        self.entries.sort_by(|a, b| a.timestamp.cmp(&b.timestamp));
+        let json = serde_json::to_string_pretty(self).expect("manifests always serialize");
+        let staging = dir.join(format!(".{MANIFEST_FILE}.tmp"));
+        std::fs::write(&staging, json)?;
+        std::fs::rename(&staging, dir.join(MANIFEST_FILE))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+    use reqwest::StatusCode;
+
+    use super::*;
+    use crate::goesimages::{self, Product, Resolution, Sat, Sector};
+    use crate::plan::{self, NameTemplate};
+
+    #[test]
+    fn records_failures_in_time_order() -> Result<(), String> {
+        let dir = tempfile::tempdir().map_err(|e| e.to_string())?;
+        let options = PlanOptions {
+            sat: Sat::GoesEast,
+            sector: Sector::FullDisk,
+            product: Product::GeoColor,
+            resolution: Resolution::Thumbnail,
+            subdirectory: dir.path().to_string_lossy().to_string(),
+            timezone_output: None,
+            name_template: NameTemplate::default(),
+            base_url: goesimages::default_base_url(),
+        };
+        let start = Utc.with_ymd_and_hms(2024, 11, 30, 8, 30, 0).unwrap();
+        let end = Utc.with_ymd_and_hms(2024, 11, 30, 8, 40, 0).unwrap();
+        let frames = plan::plan(&options, [start, end])?.entries;
+
+        let mut manifest = Manifest::new(&options, frames[0].dimensions, start, end, 10);
+        let missing = Err(DownloadError::Status {
+            url: frames[1].url.clone(),
+            status: StatusCode::NOT_FOUND,
+        });
+        manifest
+            .entries
+            .push(ManifestEntry::new(&frames[1], &missing, 3));
+        let saved = Ok(SavedImage {
+            path: frames[0].path.clone(),
+            bytes: 1234,
+            status: Some(200),
+            sha256: None,
+            etag: None,
+            already_present: false,
+            substituted: None,
+            retries: 0,
+        });
+        manifest
+            .entries
+            .push(ManifestEntry::new(&frames[0], &saved, 0));
+        manifest.write(dir.path()).map_err(|e| e.to_string())?;
+
+        let written: serde_json::Value = serde_json::from_slice(
+            &std::fs::read(dir.path().join(MANIFEST_FILE)).map_err(|e| e.to_string())?,
+        )
+        .map_err(|e| e.to_string())?;
+
+        assert_eq!("east", written["sat"]);
+        assert_eq!("339x339", written["resolution"]);
+        let entries = written["entries"].as_array().unwrap();
+        assert_eq!("20241130T083000.jpg", entries[0]["file_name"]);
+        assert_eq!("GOES16", entries[0]["sat"]);
+        assert_eq!(200, entries[0]["status"]);
+        assert_eq!(1234, entries[0]["bytes"]);
+        assert!(entries[0].get("error").is_none());
+        assert!(entries[0].get("retries").is_none());
+        assert_eq!(3, entries[1]["retries"]);
+        assert_eq!(404, entries[1]["status"]);
+        assert!(entries[1].get("bytes").is_none());
+        assert!(entries[1]["error"].as_str().unwrap().contains("404"));
+        Ok(())
+    }
+
+    #[test]
+    fn entries_name_their_own_satellite() -> Result<(), String> {
+        let options = PlanOptions {
+            sat: Sat::GoesEast,
+            sector: Sector::FullDisk,
+            product: Product::GeoColor,
+            resolution: Resolution::Medium,
+            subdirectory: String::new(),
+            timezone_output: None,
+            name_template: NameTemplate::default(),
+            base_url: goesimages::default_base_url(),
+        };
+        let before = goesimages::goes19_handover() - chrono::TimeDelta::minutes(10);
+        let after = goesimages::goes19_handover();
+        let missing = |frame: &PlannedFrame| {
+            Err(DownloadError::Status {
+                url: frame.url.clone(),
+                status: StatusCode::NOT_FOUND,
+            })
+        };
+        let entries: Vec<_> = plan::plan(&options, [before, after])?
+            .entries
+            .iter()
+            .map(|frame| ManifestEntry::new(frame, &missing(frame), 0).sat)
+            .collect();
+        assert_eq!(vec!["GOES16", "GOES19"], entries);
+        Ok(())
+    }
+}
@@ -0,0 +1,341 @@
+use std::str::FromStr;
+
+use chrono::{
+    format::{Item, StrftimeItems},
+    DateTime, Offset, Utc,
+};
+use chrono_tz::Tz;
+use url::Url;
+
+use crate::goesimages::{self, Product, Resolution, Sat, Sector};
+
+/// Everything besides the timestamps that decides which file each frame comes from and where
+/// it is written
+#[derive(Clone, Debug)]
+pub struct PlanOptions {
+    pub sat: Sat,
+    pub sector: Sector,
+    pub product: Product,
+    pub resolution: Resolution,
+    /// Directory the frames are written into
+    pub subdirectory: String,
+    /// Timezone used for output filenames, UTC when unset
+    pub timezone_output: Option<Tz>,
+    /// How each frame's file name is built
+    pub name_template: NameTemplate,
+    /// Root of the CDN or a mirror of it
+    pub base_url: Url,
+}
+
+impl PlanOptions {
+    pub fn frame_url(&self, time: &DateTime<Utc>) -> Result<Url, Box<dyn std::error::Error>> {
+        goesimages::construct_image_url_on(
+            &self.base_url,
+            &self.sat,
+            &self.sector,
+            &self.product,
+            &self.resolution,
+            time,
+        )
+    }
+}
+
+/// One frame of a run, resolved down to where it is fetched from and where it is written
+#[derive(Clone, Debug, PartialEq)]
+pub struct PlannedFrame {
+    pub timestamp: DateTime<Utc>,
+    pub sat: Sat,
+    pub product: Product,
+    pub sector: Sector,
+    pub resolution: Resolution,
+    /// `(width, height)` of the resolution in this sector
+    pub dimensions: (u32, u32),
+    pub url: Url,
+    /// The frame's file name under the subdirectory
+    pub file_name: String,
+    pub path: String,
+}
+
+/// Every frame a run will fetch, in order
+#[derive(Clone, Debug, PartialEq)]
+pub struct DownloadPlan {
+    pub entries: Vec<PlannedFrame>,
+}
+
+/// Resolves each of `times` into a [`PlannedFrame`] without touching the network or the disk
+pub fn plan(
+    options: &PlanOptions,
+    times: impl IntoIterator<Item = DateTime<Utc>>,
+) -> Result<DownloadPlan, String> {
+    let dimensions =
+        goesimages::published_dimensions(&options.sector, &options.product, &options.resolution)?;
+    let entries = times
+        .into_iter()
+        .map(|time| {
+            let url = options
+                .frame_url(&time)
+                .map_err(|e| format!("Failed to construct url for time {time}: {e}"))?;
+            let file_name = options.name_template.render(options, dimensions, &time);
+            if file_name.contains(['/', '\\']) {
+                return Err(format!(
+                    "File name '{file_name}' for time {time} contains a path separator"
+                ));
+            }
+            Ok(PlannedFrame {
+                timestamp: time,
+                sat: options.sat.operational_at(&time),
+                product: options.product,
+                sector: options.sector,
+                resolution: options.resolution,
+                dimensions,
+                url,
+                path: format!("{}/{file_name}", options.subdirectory),
+                file_name,
+            })
+        })
+        .collect::<Result<_, String>>()?;
+    Ok(DownloadPlan { entries })
+}
+
+/// File names matching what was written before templates existed, e.g. `20241130T083000.jpg`
+pub const DEFAULT_NAME_TEMPLATE: &str = "{time}.jpg";
+
+/// A file name pattern like `{sat}_{sector}_{time:%Y%j%H%M}.jpg`, checked when parsed so a typo
+/// fails before anything is downloaded.
+///
+/// | Placeholder   | Expands to                                                       |
+/// |---------------|------------------------------------------------------------------|
+/// | `{sat}`       | `GOES16`, `GOES18`                                               |
+/// | `{product}`   | `GEOCOLOR`, `AirMass`, `01`...                                   |
+/// | `{sector}`    | `FD`, `CONUS`, `M1`, `M2`                                        |
+/// | `{res}`       | `WIDTHxHEIGHT`, e.g. `1808x1808`                                 |
+/// | `{time}`      | The frame time as `%Y%m%dT%H%M%S`, with an offset if not UTC     |
+/// | `{time:FMT}`  | The frame time in the output timezone, formatted by strftime FMT |
+///
+/// `{{` and `}}` are literal braces.
+#[derive(Clone, Debug, PartialEq)]
+pub struct NameTemplate {
+    segments: Vec<Segment>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Segment {
+    Literal(String),
+    Sat,
+    Product,
+    Sector,
+    Res,
+    Time(Option<String>),
+}
+
+impl FromStr for NameTemplate {
+    type Err = String;
+
+    fn from_str(template: &str) -> Result<Self, Self::Err> {
+        let mut segments = Vec::new();
+        let mut literal = String::new();
+        let mut chars = template.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.as_str().starts_with('{') => {
+                    chars.next();
+                    literal.push('{');
+                }
+                '}' if chars.as_str().starts_with('}') => {
+                    chars.next();
+                    literal.push('}');
+                }
+                '}' => return Err(format!("Unmatched '}}' in '{template}'")),
+                '{' => {
+                    let rest = chars.as_str();
+                    let Some(end) = rest.find('}') else {
+                        return Err(format!("Unclosed '{{' in '{template}'"));
+                    };
+                    let placeholder = &rest[..end];
+                    chars = rest[end + 1..].chars();
+                    if !literal.is_empty() {
+                        segments.push(Segment::Literal(std::mem::take(&mut literal)));
+                    }
+                    segments.push(match placeholder {
+                        "sat" => Segment::Sat,
+                        "product" => Segment::Product,
+                        "sector" => Segment::Sector,
+                        "res" => Segment::Res,
+                        "time" => Segment::Time(None),
+                        _ => match placeholder.strip_prefix("time:") {
+                            Some(format)
+                                if !format.is_empty()
+                                    && !StrftimeItems::new(format)
+                                        .any(|item| matches!(item, Item::Error)) =>
+                            {
+                                Segment::Time(Some(format.to_string()))
+                            }
+                            Some(format) => return Err(format!("Invalid time format '{format}'")),
+                            None => {
+                                return Err(format!(
+                                    "Unknown placeholder '{{{placeholder}}}', expected one of \
+                                     {{sat}}, {{product}}, {{sector}}, {{res}}, {{time}} or \
+                                     {{time:FORMAT}}"
+                                ))
+                            }
+                        },
+                    });
+                }
+                '/' | '\\' => {
+                    return Err(format!("'{template}' contains a path separator"));
+                }
+                c => literal.push(c),
+            }
+        }
+        if !literal.is_empty() {
+            segments.push(Segment::Literal(literal));
+        }
+        // Without the time every frame would be written to the same file
+        if !segments.iter().any(|s| matches!(s, Segment::Time(_))) {
+            return Err(format!("'{template}' has to include {{time}}"));
+        }
+        Ok(NameTemplate { segments })
+    }
+}
+
+impl Default for NameTemplate {
+    fn default() -> Self {
+        DEFAULT_NAME_TEMPLATE
+            .parse()
+            .expect("the default template is valid")
+    }
+}
+
+impl NameTemplate {
+    /// The literal text every name ends in, like `.jpg`, unless it ends in a placeholder
+    pub fn suffix(&self) -> Option<&str> {
+        match self.segments.last() {
+            Some(Segment::Literal(text)) => Some(text),
+            _ => None,
+        }
+    }
+
+    /// Expands the template for one frame
+    pub fn render(
+        &self,
+        options: &PlanOptions,
+        dimensions: (u32, u32),
+        time: &DateTime<Utc>,
+    ) -> String {
+        let tz = options.timezone_output.as_ref();
+        self.segments
+            .iter()
+            .map(|segment| match segment {
+                Segment::Literal(text) => text.clone(),
+                Segment::Sat => options.sat.operational_at(time).url_frag().to_string(),
+                Segment::Product => options.product.url_frag().to_string(),
+                Segment::Sector => options.sector.url_frag().to_string(),
+                Segment::Res => format!("{}x{}", dimensions.0, dimensions.1),
+                Segment::Time(None) => format_output_time(time, tz),
+                Segment::Time(Some(format)) => match tz {
+                    Some(tz) => time.with_timezone(tz).format(format).to_string(),
+                    None => time.format(format).to_string(),
+                },
+            })
+            .collect()
+    }
+}
+
+/// Formats a frame time for filenames, either as a plain UTC timestamp or in the requested
+/// output timezone with an offset suffix so local names stay unambiguous
+pub fn format_output_time(time: &DateTime<Utc>, tz: Option<&Tz>) -> String {
+    match tz {
+        None => time.format("%Y%m%dT%H%M%S").to_string(),
+        Some(tz) => {
+            let local = time.with_timezone(tz);
+            if local.offset().fix().local_minus_utc() == 0 {
+                format!("{}Z", local.format("%Y%m%dT%H%M%S"))
+            } else {
+                local.format("%Y%m%dT%H%M%S%z").to_string()
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    #[test]
+    fn resolves_each_frame() -> Result<(), String> {
+        let options = PlanOptions {
+            sat: Sat::GoesWest,
+            sector: Sector::FullDisk,
+            product: Product::GeoColor,
+            resolution: Resolution::Thumbnail,
+            subdirectory: "out".to_string(),
+            timezone_output: Some(chrono_tz::America::Chicago),
+            name_template: NameTemplate::default(),
+            base_url: goesimages::default_base_url(),
+        };
+        let first = Utc.with_ymd_and_hms(2024, 11, 30, 8, 30, 0).unwrap();
+        let second = Utc.with_ymd_and_hms(2024, 11, 30, 8, 40, 0).unwrap();
+        let plan = plan(&options, [first, second])?;
+
+        assert_eq!(2, plan.entries.len());
+        let frame = &plan.entries[0];
+        assert_eq!(first, frame.timestamp);
+        assert_eq!(Sat::GoesWest, frame.sat);
+        assert_eq!(
+            (Product::GeoColor, Sector::FullDisk, (339, 339)),
+            (frame.product, frame.sector, frame.dimensions)
+        );
+        assert_eq!(
+            "https://cdn.star.nesdis.noaa.gov/GOES18/ABI/FD/GEOCOLOR/20243350830_GOES18-ABI-FD-GEOCOLOR-339x339.jpg",
+            frame.url.as_str()
+        );
+        assert_eq!("out/20241130T023000-0600.jpg", frame.path);
+        assert_eq!("out/20241130T024000-0600.jpg", plan.entries[1].path);
+        Ok(())
+    }
+
+    #[test]
+    fn expands_name_templates() -> Result<(), String> {
+        let mut options = PlanOptions {
+            sat: Sat::GoesEast,
+            sector: Sector::Meso1,
+            product: Product::Band13,
+            resolution: Resolution::Low,
+            subdirectory: "out".to_string(),
+            timezone_output: None,
+            name_template: "{sat}_{sector}_{product}_{res}_{time:%Y%j%H%M}.jpg".parse()?,
+            base_url: goesimages::default_base_url(),
+        };
+        let time = Utc.with_ymd_and_hms(2024, 11, 30, 8, 31, 0).unwrap();
+        let plan = plan(&options, [time])?;
+        assert_eq!(
+            "GOES16_M1_13_500x500_20243350831.jpg",
+            plan.entries[0].file_name
+        );
+
+        options.name_template = "{{{time}}}.jpg".parse()?;
+        assert_eq!(
+            "{20241130T083100}.jpg",
+            super::plan(&options, [time])?.entries[0].file_name
+        );
+
+        for bad in [
+            "{satellite}_{time}.jpg",
+            "{time.jpg",
+            "time}.jpg",
+            "{time:%Q}.jpg",
+            "{sat}.jpg",
+            "frames/{time}.jpg",
+        ] {
+            assert!(bad.parse::<NameTemplate>().is_err(), "{bad}");
+        }
+        assert_eq!(Some(".jpg"), NameTemplate::default().suffix());
+        assert_eq!(None, "{sat}_{time}".parse::<NameTemplate>()?.suffix());
+
+        options.name_template = "{time:%D}.jpg".parse()?;
+        assert!(super::plan(&options, [time]).is_err());
+        Ok(())
+    }
+}
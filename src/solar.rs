@@ -0,0 +1,136 @@
+use chrono::{DateTime, NaiveDate, TimeDelta, Utc};
+
+/// Sun elevation above the horizon in degrees at `time` for an observer at `lat`/`lon` (degrees,
+/// east positive).
+///
+/// Uses the low precision solar coordinates from the Astronomical Almanac (good to about 0.01°
+/// between 1950 and 2050) and ignores atmospheric refraction, which is plenty for deciding
+/// whether a frame is day or night.
+pub fn solar_elevation(time: &DateTime<Utc>, lat: f64, lon: f64) -> f64 {
+    let (hour_angle, declination) = hour_angle_and_declination(time, lon);
+    let lat = lat.to_radians();
+    (lat.sin() * declination.sin() + lat.cos() * declination.cos() * hour_angle.cos())
+        .asin()
+        .to_degrees()
+}
+
+/// Local hour angle and declination of the sun, both in radians
+fn hour_angle_and_declination(time: &DateTime<Utc>, lon: f64) -> (f64, f64) {
+    // Days since J2000.0
+    let n = time.timestamp() as f64 / 86400.0 + 2440587.5 - 2451545.0;
+
+    let mean_longitude = (280.460 + 0.9856474 * n).rem_euclid(360.0);
+    let mean_anomaly = (357.528 + 0.9856003 * n).rem_euclid(360.0).to_radians();
+    let ecliptic_longitude =
+        (mean_longitude + 1.915 * mean_anomaly.sin() + 0.020 * (2.0 * mean_anomaly).sin())
+            .to_radians();
+    let obliquity = (23.439 - 0.0000004 * n).to_radians();
+
+    let right_ascension = (obliquity.cos() * ecliptic_longitude.sin())
+        .atan2(ecliptic_longitude.cos())
+        .to_degrees();
+    let declination = (obliquity.sin() * ecliptic_longitude.sin()).asin();
+
+    let sidereal_degrees = (18.697374558 + 24.06570982441908 * n) * 15.0;
+    let hour_angle = (sidereal_degrees + lon - right_ascension).to_radians();
+
+    (hour_angle, declination)
+}
+
+/// When the sun is up at a location over one local solar day
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Daylight {
+    Between {
+        sunrise: DateTime<Utc>,
+        sunset: DateTime<Utc>,
+    },
+    /// The sun never sets
+    PolarDay,
+    /// The sun never rises
+    PolarNight,
+}
+
+/// The local solar date at `lon` that `time` falls on, i.e. the date whose solar noon is
+/// nearest to it
+pub fn solar_date(time: &DateTime<Utc>, lon: f64) -> NaiveDate {
+    (*time + TimeDelta::seconds((lon * 240.0) as i64)).date_naive()
+}
+
+/// Sunrise and sunset around solar noon of the local solar `date` at `lat`/`lon`, using the
+/// same horizon (no refraction) as [`solar_elevation`] so the two agree on what counts as day.
+///
+/// The declination is taken at solar noon, which puts the results within a minute or two of
+/// the exact crossings away from the poles.
+pub fn daylight(date: NaiveDate, lat: f64, lon: f64) -> Daylight {
+    let guess =
+        date.and_hms_opt(12, 0, 0).unwrap().and_utc() - TimeDelta::seconds((lon * 240.0) as i64);
+    // Move from the guess to the actual transit, where the hour angle is zero
+    let (hour_angle, _) = hour_angle_and_declination(&guess, lon);
+    let hour_angle = hour_angle.to_degrees().rem_euclid(360.0);
+    let hour_angle = if hour_angle > 180.0 {
+        hour_angle - 360.0
+    } else {
+        hour_angle
+    };
+    let noon = guess - TimeDelta::seconds((hour_angle * 240.0) as i64);
+
+    let (_, declination) = hour_angle_and_declination(&noon, lon);
+    let lat = lat.to_radians();
+    let cos_half_day = -(lat.tan() * declination.tan());
+    if cos_half_day <= -1.0 {
+        return Daylight::PolarDay;
+    }
+    if cos_half_day >= 1.0 {
+        return Daylight::PolarNight;
+    }
+    let half_day = TimeDelta::seconds((cos_half_day.acos().to_degrees() * 240.0) as i64);
+    Daylight::Between {
+        sunrise: noon - half_day,
+        sunset: noon + half_day,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{DurationRound, TimeZone};
+
+    use super::*;
+
+    #[test]
+    fn equinox_noon_and_midnight() {
+        let noon = Utc.with_ymd_and_hms(2024, 3, 20, 12, 0, 0).unwrap();
+        let midnight = Utc.with_ymd_and_hms(2024, 3, 20, 0, 0, 0).unwrap();
+        assert!(solar_elevation(&noon, 0.0, 0.0) > 85.0);
+        assert!(solar_elevation(&midnight, 0.0, 0.0) < -85.0);
+    }
+
+    #[test]
+    fn sunrise_and_sunset_bracket_daylight() {
+        // Denver, a summer's day
+        let date = NaiveDate::from_ymd_opt(2024, 6, 21).unwrap();
+        let Daylight::Between { sunrise, sunset } = daylight(date, 39.7, -105.0) else {
+            panic!("expected a sunrise and sunset");
+        };
+        // Roughly 11:30Z and 02:30Z the next day, ignoring refraction
+        assert_eq!(
+            Utc.with_ymd_and_hms(2024, 6, 21, 11, 30, 0).unwrap(),
+            sunrise.duration_round(TimeDelta::minutes(30)).unwrap()
+        );
+        assert_eq!(
+            Utc.with_ymd_and_hms(2024, 6, 22, 2, 30, 0).unwrap(),
+            sunset.duration_round(TimeDelta::minutes(30)).unwrap()
+        );
+        assert!(solar_elevation(&(sunrise - TimeDelta::minutes(5)), 39.7, -105.0) < 0.0);
+        assert!(solar_elevation(&(sunrise + TimeDelta::minutes(5)), 39.7, -105.0) > 0.0);
+        assert!(solar_elevation(&(sunset - TimeDelta::minutes(5)), 39.7, -105.0) > 0.0);
+        assert!(solar_elevation(&(sunset + TimeDelta::minutes(5)), 39.7, -105.0) < 0.0);
+        assert_eq!(date, solar_date(&sunset, -105.0));
+    }
+
+    #[test]
+    fn polar_day_and_night() {
+        let june = NaiveDate::from_ymd_opt(2024, 6, 21).unwrap();
+        assert_eq!(Daylight::PolarDay, daylight(june, 78.2, 15.6));
+        assert_eq!(Daylight::PolarNight, daylight(june, -78.2, 15.6));
+    }
+}
@@ -0,0 +1,67 @@
+use std::{fs::File, io::BufWriter, path::Path};
+
+use chrono::{DateTime, Utc};
+use tiff::{
+    encoder::{colortype::RGB8, compression::DeflateLevel, Compression, TiffEncoder},
+    tags::Tag,
+};
+
+/// Writes the given JPEG frames, in order, as the pages of one multi-page TIFF.
+///
+/// Each page is tagged with its frame time, both as the TIFF `DateTime` and in RFC 3339 as the
+/// `ImageDescription` since the former can't carry a timezone. Pages are Deflate compressed.
+/// This decodes every frame, so call it from a blocking thread.
+pub fn encode_tiff_stack<P: AsRef<Path>>(
+    frames: &[(DateTime<Utc>, P)],
+    output: &Path,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let mut encoder = TiffEncoder::new(BufWriter::new(File::create(output)?))?
+        .with_compression(Compression::Deflate(DeflateLevel::Balanced));
+    for (time, path) in frames {
+        let frame = image::open(path)?.to_rgb8();
+        let mut page = encoder.new_image::<RGB8>(frame.width(), frame.height())?;
+        page.encoder().write_tag(
+            Tag::DateTime,
+            time.format("%Y:%m:%d %H:%M:%S").to_string().as_str(),
+        )?;
+        page.encoder()
+            .write_tag(Tag::ImageDescription, time.to_rfc3339().as_str())?;
+        page.write_data(frame.as_raw())?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+    use image::{Rgb, RgbImage};
+
+    use super::*;
+
+    #[test]
+    fn writes_one_page_per_frame() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let dir = tempfile::tempdir()?;
+        let frames: Vec<_> = (0..3u32)
+            .map(|i| {
+                let path = dir.path().join(format!("{i}.jpg"));
+                let time = Utc.with_ymd_and_hms(2024, 11, 30, 8, 10 * i, 0).unwrap();
+                RgbImage::from_pixel(16, 16, Rgb([200, 0, 0]))
+                    .save(&path)
+                    .map(|_| (time, path))
+            })
+            .collect::<Result<_, _>>()?;
+        let output = dir.path().join("stack.tiff");
+        encode_tiff_stack(&frames, &output)?;
+
+        let mut decoder = tiff::decoder::Decoder::new(File::open(&output)?)?;
+        let mut pages = 1;
+        while decoder.more_images() {
+            decoder.next_image()?;
+            pages += 1;
+        }
+        let description = decoder.get_tag_ascii_string(Tag::ImageDescription)?;
+        assert_eq!(3, pages);
+        assert_eq!("2024-11-30T08:20:00+00:00", description);
+        Ok(())
+    }
+}